@@ -0,0 +1,10 @@
+-- Highlights and quotes, they are deleted together with their book
+CREATE TABLE quotes (
+	id INTEGER NOT NULL PRIMARY KEY,
+	book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+	text VARCHAR NOT NULL,
+	location VARCHAR,
+	added_at TIMESTAMP
+);
+
+CREATE INDEX quotes_book_id ON quotes(book_id);
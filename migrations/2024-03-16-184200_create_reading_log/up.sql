@@ -0,0 +1,8 @@
+-- Time spent reading a book per day, filled in by e-reader imports
+CREATE TABLE reading_log (
+	book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+	day DATE NOT NULL,
+	seconds INTEGER NOT NULL,
+	pages INTEGER NOT NULL,
+	PRIMARY KEY (book_id, day)
+)
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`, only the latest session of a book is kept
+DROP VIEW book_overview;
+
+ALTER TABLE books ADD COLUMN start_date DATE;
+ALTER TABLE books ADD COLUMN end_date DATE;
+
+UPDATE books SET
+	start_date = (SELECT s.started FROM reading_sessions s WHERE s.book_id = books.id
+		ORDER BY coalesce(s.started, s.finished) DESC, s.id DESC LIMIT 1),
+	end_date = (SELECT s.finished FROM reading_sessions s WHERE s.book_id = books.id
+		ORDER BY coalesce(s.started, s.finished) DESC, s.id DESC LIMIT 1);
+
+DROP TABLE reading_sessions;
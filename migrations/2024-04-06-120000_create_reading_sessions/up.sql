@@ -0,0 +1,39 @@
+-- Every time a book is read is a session, so rereads keep their history
+CREATE TABLE reading_sessions (
+	id INTEGER NOT NULL PRIMARY KEY,
+	book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+	started DATE,
+	finished DATE,
+	-- 'finished' or 'abandoned', NULL while the book is still being read
+	outcome VARCHAR,
+	pages INTEGER,
+	minutes INTEGER
+);
+
+CREATE INDEX reading_sessions_book_id ON reading_sessions(book_id);
+
+-- Each start/end date pair becomes one session
+INSERT INTO reading_sessions (book_id, started, finished, outcome)
+SELECT id, start_date, end_date,
+	CASE
+		WHEN lower(status) IN ('abandoned', 'did-not-finish', 'dnf') THEN 'abandoned'
+		WHEN end_date IS NOT NULL THEN 'finished'
+	END
+FROM books
+WHERE start_date IS NOT NULL OR end_date IS NOT NULL;
+
+ALTER TABLE books DROP COLUMN start_date;
+ALTER TABLE books DROP COLUMN end_date;
+
+-- A book with the dates of its latest session and how often it was finished
+CREATE VIEW book_overview AS
+SELECT b.id, b.title, b.author, b.genre, b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE books ADD COLUMN author VARCHAR NOT NULL DEFAULT '';
+UPDATE books SET author = (SELECT author FROM book_overview o WHERE o.id = books.id);
+
+DROP VIEW book_overview;
+DROP TABLE IF EXISTS author_backfill;
+DROP TABLE book_authors;
+DROP TABLE authors;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title, b.author, b.genre, b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
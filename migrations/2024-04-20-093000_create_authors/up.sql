@@ -0,0 +1,44 @@
+-- People are stored once and linked to their books with a role
+CREATE TABLE authors (
+	id INTEGER NOT NULL PRIMARY KEY,
+	name VARCHAR NOT NULL,
+	-- The name without case, spaces and punctuation, "J. R. R. Tolkien" is "jrrtolkien"
+	key VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE book_authors (
+	book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+	author_id INTEGER NOT NULL REFERENCES authors(id) ON DELETE CASCADE,
+	-- 'author', 'translator' or 'editor'
+	role VARCHAR NOT NULL DEFAULT 'author',
+	position INTEGER NOT NULL DEFAULT 0,
+	PRIMARY KEY (book_id, author_id, role)
+);
+
+CREATE INDEX book_authors_author_id ON book_authors(author_id);
+
+-- Splitting "Pratchett, Terry & Neil Gaiman" is done in Rust, see
+-- database::backfill_authors, which drops this table when it is done
+CREATE TABLE author_backfill AS SELECT id AS book_id, author FROM books;
+
+DROP VIEW book_overview;
+ALTER TABLE books DROP COLUMN author;
+
+-- The author of a book is everyone linked to it, like "Haruki Murakami & Jay Rubin (translator)"
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	b.genre, b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
@@ -0,0 +1,28 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE books ADD COLUMN genre VARCHAR NOT NULL DEFAULT '';
+UPDATE books SET genre = (SELECT tags FROM book_overview o WHERE o.id = books.id);
+
+DROP VIEW book_overview;
+DROP TABLE IF EXISTS tag_backfill;
+DROP TABLE shelf_tags;
+DROP TABLE shelves;
+DROP TABLE book_tags;
+DROP TABLE tags;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	b.genre, b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
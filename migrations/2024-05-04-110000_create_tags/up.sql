@@ -0,0 +1,56 @@
+-- A book can have any number of tags instead of a single genre
+CREATE TABLE tags (
+	id INTEGER NOT NULL PRIMARY KEY,
+	name VARCHAR NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE book_tags (
+	book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+	tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+	PRIMARY KEY (book_id, tag_id)
+);
+
+CREATE INDEX book_tags_tag_id ON book_tags(tag_id);
+
+-- A shelf is a saved tag filter, it holds the books with any or all of its tags
+CREATE TABLE shelves (
+	id INTEGER NOT NULL PRIMARY KEY,
+	name VARCHAR NOT NULL UNIQUE COLLATE NOCASE,
+	match_all BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE TABLE shelf_tags (
+	shelf_id INTEGER NOT NULL REFERENCES shelves(id) ON DELETE CASCADE,
+	tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+	PRIMARY KEY (shelf_id, tag_id)
+);
+
+-- Genres like "fantasy, classics" are split in Rust, see
+-- database::backfill_tags, which drops this table when it is done
+CREATE TABLE tag_backfill AS SELECT id AS book_id, genre AS tags FROM books;
+
+DROP VIEW book_overview;
+ALTER TABLE books DROP COLUMN genre;
+
+-- The tags of a book are shown as one string, like "book club, fantasy"
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
@@ -0,0 +1,34 @@
+-- This file should undo anything in `up.sql`
+-- The series goes back into the title, the way it was typed before
+UPDATE books SET title = title || ' (' || (SELECT name FROM series WHERE id = books.series_id)
+	|| CASE WHEN series_index IS NULL THEN '' ELSE ', #' || printf('%g', series_index) END || ')'
+WHERE series_id IS NOT NULL;
+
+DROP VIEW book_overview;
+DROP TABLE IF EXISTS series_backfill;
+DROP INDEX books_series_id;
+ALTER TABLE books DROP COLUMN series_index;
+ALTER TABLE books DROP COLUMN series_id;
+DROP TABLE series;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
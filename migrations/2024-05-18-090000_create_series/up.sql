@@ -0,0 +1,43 @@
+-- A book is a volume of at most one series, the index puts the volumes in order
+CREATE TABLE series (
+	id INTEGER NOT NULL PRIMARY KEY,
+	name VARCHAR NOT NULL UNIQUE COLLATE NOCASE
+);
+
+-- No foreign key, sqlite can't drop a column that has one and down.sql has to.
+-- A series is only deleted once no book is in it, see database::delete_orphan_series
+ALTER TABLE books ADD COLUMN series_id INTEGER;
+-- Not an integer, novellas are often numbered like 2.5
+ALTER TABLE books ADD COLUMN series_index DOUBLE;
+
+CREATE INDEX books_series_id ON books(series_id);
+
+-- Titles like "Oathbringer (The Stormlight Archive, #3)" are split in Rust,
+-- see database::backfill_series, which drops this table when it is done
+CREATE TABLE series_backfill AS SELECT id AS book_id, title FROM books;
+
+DROP VIEW book_overview;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	(SELECT se.name FROM series se WHERE se.id = b.series_id) AS series,
+	b.series_index,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
@@ -0,0 +1,32 @@
+-- This file should undo anything in `up.sql`
+DROP VIEW book_overview;
+ALTER TABLE books DROP COLUMN format;
+ALTER TABLE books DROP COLUMN language;
+ALTER TABLE books DROP COLUMN pages;
+ALTER TABLE books DROP COLUMN published_year;
+ALTER TABLE books DROP COLUMN publisher;
+ALTER TABLE books DROP COLUMN isbn;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	(SELECT se.name FROM series se WHERE se.id = b.series_id) AS series,
+	b.series_index,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
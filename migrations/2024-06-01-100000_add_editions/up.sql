@@ -0,0 +1,37 @@
+-- The edition of a book, every field is optional
+-- Always an ISBN-13 without dashes, ISBN-10s are converted, see editions::parse_isbn
+ALTER TABLE books ADD COLUMN isbn VARCHAR;
+ALTER TABLE books ADD COLUMN publisher VARCHAR;
+ALTER TABLE books ADD COLUMN published_year INTEGER;
+-- Page count of the edition, the pages read are kept per reading session
+ALTER TABLE books ADD COLUMN pages INTEGER;
+ALTER TABLE books ADD COLUMN language VARCHAR;
+-- hardcover, paperback, ebook or audiobook, see editions::FORMATS
+ALTER TABLE books ADD COLUMN format VARCHAR;
+
+DROP VIEW book_overview;
+
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	(SELECT se.name FROM series se WHERE se.id = b.series_id) AS series,
+	b.series_index,
+	b.isbn, b.publisher, b.published_year, b.pages, b.language, b.format,
+	s.started AS start_date, s.finished AS end_date,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+);
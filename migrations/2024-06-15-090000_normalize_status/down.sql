@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+-- The other spellings are lost, go back to the names booky used to write
+UPDATE books SET status = CASE status
+	WHEN 'want-to-read' THEN 'to-read'
+	WHEN 'finished' THEN 'read'
+	ELSE status
+END;
@@ -0,0 +1,34 @@
+-- The status used to be free text, so "Read", "read" and "Done" all meant the
+-- same thing. Every spelling booky knows becomes one of the statuses of
+-- status::ReadingStatus, anything else is worked out from the reading sessions.
+-- sqlite can't add a CHECK constraint to an existing table, the Rust type
+-- refuses to load any other value instead.
+UPDATE books SET status = CASE replace(replace(lower(trim(status)), ' ', '-'), '_', '-')
+	WHEN 'want-to-read' THEN 'want-to-read'
+	WHEN 'to-read' THEN 'want-to-read'
+	WHEN 'tbr' THEN 'want-to-read'
+	WHEN 'unread' THEN 'want-to-read'
+	WHEN 'reading' THEN 'reading'
+	WHEN 'currently-reading' THEN 'reading'
+	WHEN 'started' THEN 'reading'
+	WHEN 'in-progress' THEN 'reading'
+	WHEN 'paused' THEN 'paused'
+	WHEN 'on-hold' THEN 'paused'
+	WHEN 'finished' THEN 'finished'
+	WHEN 'read' THEN 'finished'
+	WHEN 'done' THEN 'finished'
+	WHEN 'completed' THEN 'finished'
+	WHEN 'complete' THEN 'finished'
+	WHEN 'abandoned' THEN 'abandoned'
+	WHEN 'dnf' THEN 'abandoned'
+	WHEN 'did-not-finish' THEN 'abandoned'
+	WHEN 'dropped' THEN 'abandoned'
+	ELSE CASE
+		WHEN EXISTS (SELECT 1 FROM reading_sessions s
+			WHERE s.book_id = books.id AND s.outcome = 'finished') THEN 'finished'
+		WHEN EXISTS (SELECT 1 FROM reading_sessions s
+			WHERE s.book_id = books.id AND s.outcome = 'abandoned') THEN 'abandoned'
+		WHEN EXISTS (SELECT 1 FROM reading_sessions s WHERE s.book_id = books.id) THEN 'reading'
+		ELSE 'want-to-read'
+	END
+END;
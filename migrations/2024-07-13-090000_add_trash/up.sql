@@ -0,0 +1,35 @@
+-- Deleting a book moves it to the trash, it is only removed for good when
+-- it is purged by hand or has been in the trash longer than the retention
+-- period of the config file. NULL for books that aren't deleted.
+ALTER TABLE books ADD COLUMN deleted_at TIMESTAMP;
+
+DROP VIEW book_overview;
+
+-- Books in the trash are left out
+CREATE VIEW book_overview AS
+SELECT b.id, b.title,
+	coalesce((SELECT group_concat(name, ' & ') FROM (
+		SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+		FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+		WHERE ba.book_id = b.id
+		ORDER BY ba.position)), '') AS author,
+	coalesce((SELECT group_concat(name, ', ') FROM (
+		SELECT t.name FROM book_tags bt JOIN tags t ON t.id = bt.tag_id
+		WHERE bt.book_id = b.id
+		ORDER BY t.name)), '') AS tags,
+	b.rating, b.status,
+	(SELECT se.name FROM series se WHERE se.id = b.series_id) AS series,
+	b.series_index,
+	b.isbn, b.publisher, b.published_year, b.pages, b.language, b.format,
+	s.started AS start_date, s.finished AS end_date,
+	coalesce(s.started_precision, 'day') AS start_precision,
+	coalesce(s.finished_precision, 'day') AS end_precision,
+	(SELECT count(*) FROM reading_sessions f
+		WHERE f.book_id = b.id AND f.outcome = 'finished') AS times_read
+FROM books b
+LEFT JOIN reading_sessions s ON s.id = (
+	SELECT l.id FROM reading_sessions l WHERE l.book_id = b.id
+	ORDER BY coalesce(l.started, l.finished) DESC, l.id DESC
+	LIMIT 1
+)
+WHERE b.deleted_at IS NULL;
@@ -1,38 +1,49 @@
-use crate::database::models::Book;
+use crate::columns::Column;
+use crate::database::models::{Book, BookQuery, DeletedBook, SeriesSummary};
+use crate::database::Library;
+use crate::dates::DateOrder;
+use crate::error::BookyResult;
+use crate::form::{parse_date, parse_rating, BookForm, FormErrors, FormField};
+use crate::status::ReadingStatus;
+use crate::tags::{complete_tag, TagMatch};
 use int_enum::IntEnum;
 use std::error;
 
-use tui::widgets::TableState;
+use tui::widgets::{ListState, TableState};
 use tui_textarea::TextArea;
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-pub const EDIT_WINDOW_FOCUS: i8 = 9;
-pub const SEARCH_WINDOW_FOCUS: i8 = 9;
+pub const EDIT_WINDOW_FOCUS: i8 = 17;
+pub const SEARCH_WINDOW_FOCUS: i8 = 10;
 
 #[repr(i8)]
 #[derive(Debug, IntEnum, Clone, Copy)]
 pub enum SearchFieldFocus {
     Title = 0,
     Author = 1,
-    Genre = 2,
-    Rating = 3,
-    Status = 4,
-    StartDate = 5,
-    EndDate = 6,
-    ConfirmBtn = 7,
-    CancelBtn = 8,
+    Tags = 2,
+    TagMatch = 3,
+    Rating = 4,
+    Status = 5,
+    StartDate = 6,
+    EndDate = 7,
+    ConfirmBtn = 8,
+    CancelBtn = 9,
 }
 
 pub struct SearchState<'a> {
     pub title: TextArea<'a>,
     pub author: TextArea<'a>,
-    pub genre: TextArea<'a>,
+    pub tags: TextArea<'a>,
     pub rating: TextArea<'a>,
-    pub status: TextArea<'a>,
+    /// `None` finds books with any status.
+    pub status: Option<ReadingStatus>,
     pub start_date: TextArea<'a>,
     pub end_date: TextArea<'a>,
+    /// Whether books need any or all of the tags.
+    pub tag_match: TagMatch,
     pub focus: SearchFieldFocus,
 }
 
@@ -41,40 +52,104 @@ impl Default for SearchState<'_> {
         SearchState {
             title: TextArea::default(),
             author: TextArea::default(),
-            genre: TextArea::default(),
+            tags: TextArea::default(),
             rating: TextArea::default(),
-            status: TextArea::default(),
+            status: None,
             start_date: TextArea::default(),
             end_date: TextArea::default(),
+            tag_match: TagMatch::Any,
             focus: SearchFieldFocus::Title,
         }
     }
 }
 
+impl SearchState<'_> {
+    /// Collects what the user typed into a [`BookQuery`], empty dates match every book.
+    ///
+    /// A date known to the month or year covers all of it, an end date of
+    /// "2023" finds books finished on or before December 31st.
+    pub fn to_query(&self, order: DateOrder) -> BookyResult<BookQuery> {
+        let start = parse_date(&self.start_date.lines().join("\n"), "Start date", order)?;
+        let end = parse_date(&self.end_date.lines().join("\n"), "End date", order)?;
+        let rating = parse_rating(&self.rating.lines().join("\n"))?;
+        Ok(BookQuery {
+            title: self.title.lines().join("\n"),
+            author: self.author.lines().join("\n"),
+            tags: self.tags.lines().join("\n"),
+            rating,
+            status: self.status,
+            start_date: start.map(|start| start.date),
+            end_date: end.map(|end| end.last_day()),
+        })
+    }
+}
+
 #[repr(i8)]
-#[derive(Debug, IntEnum, Clone, Copy)]
+#[derive(Debug, IntEnum, Clone, Copy, PartialEq, Eq)]
 pub enum BookEditFocus {
     Title = 0,
     Author = 1,
-    Genre = 2,
-    Rating = 3,
-    Status = 4,
-    StartDate = 5,
-    EndDate = 6,
-    ConfirmBtn = 7,
-    CancelBtn = 8,
+    Tags = 2,
+    Series = 3,
+    SeriesIndex = 4,
+    Isbn = 5,
+    Format = 6,
+    Publisher = 7,
+    PublishedYear = 8,
+    Language = 9,
+    Pages = 10,
+    Rating = 11,
+    Status = 12,
+    StartDate = 13,
+    EndDate = 14,
+    ConfirmBtn = 15,
+    CancelBtn = 16,
+}
+
+impl BookEditFocus {
+    /// The form field edited with this focus, the buttons have none.
+    pub fn field(self) -> Option<FormField> {
+        match self {
+            BookEditFocus::Title => Some(FormField::Title),
+            BookEditFocus::Author => Some(FormField::Author),
+            BookEditFocus::Tags => Some(FormField::Tags),
+            BookEditFocus::Series => Some(FormField::Series),
+            BookEditFocus::SeriesIndex => Some(FormField::SeriesIndex),
+            BookEditFocus::Isbn => Some(FormField::Isbn),
+            BookEditFocus::Format => Some(FormField::Format),
+            BookEditFocus::Publisher => Some(FormField::Publisher),
+            BookEditFocus::PublishedYear => Some(FormField::PublishedYear),
+            BookEditFocus::Language => Some(FormField::Language),
+            BookEditFocus::Pages => Some(FormField::Pages),
+            BookEditFocus::Rating => Some(FormField::Rating),
+            BookEditFocus::Status => Some(FormField::Status),
+            BookEditFocus::StartDate => Some(FormField::StartDate),
+            BookEditFocus::EndDate => Some(FormField::EndDate),
+            BookEditFocus::ConfirmBtn | BookEditFocus::CancelBtn => None,
+        }
+    }
 }
 
 pub struct BookState<'a> {
     pub title: TextArea<'a>,
     pub author: TextArea<'a>,
-    pub genre: TextArea<'a>,
+    pub tags: TextArea<'a>,
+    pub series: TextArea<'a>,
+    pub series_index: TextArea<'a>,
+    pub isbn: TextArea<'a>,
+    pub publisher: TextArea<'a>,
+    pub published_year: TextArea<'a>,
+    pub pages: TextArea<'a>,
+    pub language: TextArea<'a>,
+    pub format: TextArea<'a>,
     pub rating: TextArea<'a>,
-    pub status: TextArea<'a>,
+    pub status: ReadingStatus,
     pub start_date: TextArea<'a>,
     pub end_date: TextArea<'a>,
     pub focus: BookEditFocus,
     pub is_edit: bool,
+    /// Shown beneath the fields, the popup can't be confirmed while there are any.
+    pub errors: FormErrors,
 }
 
 impl Default for BookState<'_> {
@@ -82,50 +157,321 @@ impl Default for BookState<'_> {
         BookState {
             title: TextArea::default(),
             author: TextArea::default(),
-            genre: TextArea::default(),
+            tags: TextArea::default(),
+            series: TextArea::default(),
+            series_index: TextArea::default(),
+            isbn: TextArea::default(),
+            publisher: TextArea::default(),
+            published_year: TextArea::default(),
+            pages: TextArea::default(),
+            language: TextArea::default(),
+            format: TextArea::default(),
             rating: TextArea::default(),
-            status: TextArea::default(),
+            status: ReadingStatus::default(),
             start_date: TextArea::default(),
             end_date: TextArea::default(),
             focus: BookEditFocus::Title,
             is_edit: false,
+            errors: FormErrors::default(),
         }
     }
 }
 
+impl BookState<'_> {
+    /// Creates the popup state with every field filled in from `form`.
+    pub fn from_form(form: BookForm, is_edit: bool) -> Self {
+        BookState {
+            title: TextArea::from(form.title.lines()),
+            author: TextArea::from(form.author.lines()),
+            tags: TextArea::from(form.tags.lines()),
+            series: TextArea::from(form.series.lines()),
+            series_index: TextArea::from(form.series_index.lines()),
+            isbn: TextArea::from(form.isbn.lines()),
+            publisher: TextArea::from(form.publisher.lines()),
+            published_year: TextArea::from(form.published_year.lines()),
+            pages: TextArea::from(form.pages.lines()),
+            language: TextArea::from(form.language.lines()),
+            format: TextArea::from(form.format.lines()),
+            rating: TextArea::from(form.rating.lines()),
+            status: ReadingStatus::parse(&form.status).unwrap_or_default(),
+            start_date: TextArea::from(form.start_date.lines()),
+            end_date: TextArea::from(form.end_date.lines()),
+            focus: BookEditFocus::Title,
+            is_edit,
+            errors: FormErrors::default(),
+        }
+    }
+
+    /// Returns the known tags that complete the tag being typed.
+    pub fn tag_suggestions<'t>(&self, known: &'t [String]) -> Vec<&'t str> {
+        complete_tag(known, &self.tags.lines().join("\n"))
+    }
+
+    /// Checks every field, see [`BookForm::validate`].
+    pub fn validate(&mut self, order: DateOrder) {
+        self.errors = self.to_form().validate(order).err().unwrap_or_default();
+    }
+
+    /// Checks the field that is losing the focus.
+    ///
+    /// Errors of other fields are only cleared once they are fixed, so the
+    /// user isn't told about fields they didn't get to yet.
+    pub fn check_focused(&mut self, order: DateOrder) {
+        let Some(focused) = self.focus.field() else {
+            return;
+        };
+        let mut errors = self.to_form().validate(order).err().unwrap_or_default();
+        let shown = &self.errors;
+        errors.retain(|field| field == focused || shown.get(field).is_some());
+        self.errors = errors;
+    }
+
+    /// Collects what the user typed into a [`BookForm`].
+    pub fn to_form(&self) -> BookForm {
+        BookForm {
+            title: self.title.lines().join("\n"),
+            author: self.author.lines().join("\n"),
+            tags: self.tags.lines().join("\n"),
+            rating: self.rating.lines().join("\n"),
+            status: self.status.to_string(),
+            series: self.series.lines().join("\n"),
+            series_index: self.series_index.lines().join("\n"),
+            isbn: self.isbn.lines().join("\n"),
+            publisher: self.publisher.lines().join("\n"),
+            published_year: self.published_year.lines().join("\n"),
+            pages: self.pages.lines().join("\n"),
+            language: self.language.lines().join("\n"),
+            format: self.format.lines().join("\n"),
+            start_date: self.start_date.lines().join("\n"),
+            end_date: self.end_date.lines().join("\n"),
+        }
+    }
+}
+
+/// State of the series popup, the volumes are those of the selected series.
+pub struct SeriesState {
+    pub series: Vec<SeriesSummary>,
+    pub list_state: ListState,
+    /// Volumes of the selected series in order.
+    pub volumes: Vec<Book>,
+    /// Id of the first volume of the selected series that wasn't read yet.
+    pub next_unread: Option<i32>,
+}
+
+impl SeriesState {
+    /// Loads every series and selects the first one.
+    pub fn load(library: &mut Library) -> BookyResult<Self> {
+        let mut state = SeriesState {
+            series: library.series_list()?,
+            list_state: ListState::default(),
+            volumes: Vec::new(),
+            next_unread: None,
+        };
+        if !state.series.is_empty() {
+            state.select(library, 0)?;
+        }
+        Ok(state)
+    }
+
+    /// Selects the series at `index` and loads its volumes.
+    pub fn select(&mut self, library: &mut Library, index: usize) -> BookyResult<()> {
+        let Some(series) = self.series.get(index) else {
+            return Ok(());
+        };
+        self.volumes = library.series_books(series.id)?;
+        self.next_unread = library.next_unread(series.id)?.map(|book| book.id);
+        self.list_state.select(Some(index));
+        Ok(())
+    }
+
+    /// Selects the next series, or the previous one when `forward` is false.
+    pub fn cycle(&mut self, library: &mut Library, forward: bool) -> BookyResult<()> {
+        if self.series.is_empty() {
+            return Ok(());
+        }
+        let len = self.series.len();
+        let current = self.list_state.selected().unwrap_or_default();
+        let index = if forward {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+        self.select(library, index)
+    }
+}
+
+/// State of the trash popup.
+pub struct TrashState {
+    /// The last deleted book first.
+    pub books: Vec<DeletedBook>,
+    pub table_state: TableState,
+    /// The selected book is purged for good once this is confirmed.
+    pub confirm_purge: bool,
+}
+
+impl TrashState {
+    /// Loads the trash and selects the first book.
+    pub fn load(library: &mut Library) -> BookyResult<Self> {
+        let mut state = TrashState {
+            books: Vec::new(),
+            table_state: TableState::default(),
+            confirm_purge: false,
+        };
+        state.reload(library)?;
+        Ok(state)
+    }
+
+    /// Loads the trash again after books were restored or purged.
+    pub fn reload(&mut self, library: &mut Library) -> BookyResult<()> {
+        self.books = library.trash()?;
+        self.confirm_purge = false;
+        let selected = match self.table_state.selected() {
+            _ if self.books.is_empty() => None,
+            Some(i) => Some(i.min(self.books.len() - 1)),
+            None => Some(0),
+        };
+        self.table_state.select(selected);
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&DeletedBook> {
+        self.table_state.selected().and_then(|i| self.books.get(i))
+    }
+
+    /// Selects the next book, or the previous one when `forward` is false.
+    pub fn cycle(&mut self, forward: bool) {
+        if self.books.is_empty() {
+            return;
+        }
+        let len = self.books.len();
+        let current = self.table_state.selected().unwrap_or_default();
+        let index = if forward {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+        self.table_state.select(Some(index));
+    }
+}
+
+/// Message shown in the "Log" footer.
+#[derive(Debug, Clone)]
+pub enum LogMessage {
+    Info(String),
+    Error(String),
+}
+
 /// Application.
 pub struct App<'a> {
     pub running: bool,
     pub add_popup: bool,
     pub help_popup: bool,
     pub search_popup: bool,
+    pub series_popup: bool,
+    /// Asks before the selected book is moved to the trash.
+    pub delete_popup: bool,
+    pub trash_popup: bool,
     pub search_active: bool,
     pub state: TableState,
     pub book_edit_state: Option<BookState<'a>>,
     pub search_field_state: Option<SearchState<'a>>,
+    pub series_state: Option<SeriesState>,
+    pub trash_state: Option<TrashState>,
+    pub search_query: Option<BookQuery>,
+    pub search_tag_match: TagMatch,
     pub items: Vec<Book>,
+    /// Columns of the book table.
+    pub columns: Vec<Column>,
+    /// How typed dates like `12/04/2023` are read.
+    pub date_order: DateOrder,
+    /// Every tag in the library, used to complete tags in the add/update popup.
+    pub tags: Vec<String>,
+    pub log: Option<LogMessage>,
+    pub library: Library,
 }
 
-impl Default for App<'_> {
-    fn default() -> Self {
+impl<'a> App<'a> {
+    /// Constructs a new instance of [`App`] that shows the books in `library`.
+    pub fn new(library: Library) -> Self {
         Self {
             running: true,
             add_popup: false,
             help_popup: false,
             search_popup: false,
+            series_popup: false,
+            delete_popup: false,
+            trash_popup: false,
             search_active: false,
             state: TableState::default(),
             book_edit_state: None,
             search_field_state: None,
+            series_state: None,
+            trash_state: None,
+            search_query: None,
+            search_tag_match: TagMatch::Any,
             items: Vec::new(),
+            columns: Column::DEFAULT.to_vec(),
+            date_order: DateOrder::default(),
+            tags: Vec::new(),
+            log: None,
+            library,
         }
     }
-}
 
-impl<'a> App<'a> {
-    /// Constructs a new instance of [`App`].
-    pub fn new() -> Self {
-        Self::default()
+    /// Reloads [`App::items`] from the database.
+    ///
+    /// Call this after every change to the books table, the ui only
+    /// renders what is in `items` and never queries the database itself.
+    pub fn reload(&mut self) -> BookyResult<()> {
+        self.items = match &self.search_query {
+            Some(query) if self.search_active => {
+                self.library.search(query, self.search_tag_match)?
+            }
+            _ => self.library.books()?,
+        };
+        self.tags = self
+            .library
+            .tags()?
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+
+        // Keep the selection inside the table after books were removed
+        match self.state.selected() {
+            Some(_) if self.items.is_empty() => self.state.select(None),
+            Some(i) if i >= self.items.len() => self.state.select(Some(self.items.len() - 1)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Runs a search and shows its results until the search is cleared.
+    pub fn search(&mut self, query: BookQuery, tag_match: TagMatch) -> BookyResult<()> {
+        self.search_query = Some(query);
+        self.search_tag_match = tag_match;
+        self.search_active = true;
+        self.reload()
+    }
+
+    /// Clears the active search and shows all books again.
+    pub fn clear_search(&mut self) -> BookyResult<()> {
+        self.search_query = None;
+        self.search_active = false;
+        self.reload()
+    }
+
+    /// Shows an error in the log instead of crashing booky.
+    pub fn log_error(&mut self, error: impl std::fmt::Display) {
+        self.log = Some(LogMessage::Error(error.to_string()));
+    }
+
+    pub fn log_info(&mut self, message: impl Into<String>) {
+        self.log = Some(LogMessage::Info(message.into()));
+    }
+
+    /// Returns the currently highlighted book.
+    pub fn selected_book(&self) -> Option<&Book> {
+        self.state.selected().and_then(|i| self.items.get(i))
     }
 
     /// Handles the tick event of the terminal.
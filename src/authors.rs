@@ -0,0 +1,164 @@
+use std::fmt;
+
+/// What a person did for a book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorRole {
+    Author,
+    Translator,
+    Editor,
+}
+
+impl AuthorRole {
+    /// The role as it is stored in `book_authors.role`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthorRole::Author => "author",
+            AuthorRole::Translator => "translator",
+            AuthorRole::Editor => "editor",
+        }
+    }
+
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix.trim().trim_end_matches('.').to_lowercase().as_str() {
+            "author" | "writer" => Some(AuthorRole::Author),
+            "translator" | "tr" | "trans" | "translated by" => Some(AuthorRole::Translator),
+            "editor" | "ed" | "eds" | "edited by" => Some(AuthorRole::Editor),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AuthorRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A person in an author string, like "Jay Rubin (translator)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credit {
+    pub name: String,
+    pub role: AuthorRole,
+}
+
+/// Splits an author string into the people in it.
+///
+/// People are separated by `&`, `;` or ` and `, a role follows the name in
+/// parentheses. A single comma after a single word is a name written last
+/// name first, so "Pratchett, Terry" is "Terry Pratchett", other commas
+/// separate people like in "Terry Pratchett, Neil Gaiman". Every person is
+/// only returned once.
+pub fn split_authors(text: &str) -> Vec<Credit> {
+    let mut credits: Vec<Credit> = Vec::new();
+
+    for part in text.split(['&', ';']).flat_map(|part| part.split(" and ")) {
+        let (names, role) = strip_role(part.trim());
+        let names: Vec<String> = match names.split_once(',') {
+            Some((last, first)) if !first.contains(',') && !last.trim().contains(' ') => {
+                vec![format!("{} {}", first, last)]
+            }
+            _ => names.split(',').map(str::to_string).collect(),
+        };
+
+        for name in names {
+            let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+            let duplicate = credits
+                .iter()
+                .any(|credit| credit.role == role && author_key(&credit.name) == author_key(&name));
+            if !name.is_empty() && !duplicate {
+                credits.push(Credit { name, role });
+            }
+        }
+    }
+    credits
+}
+
+/// Writes people the way [`split_authors`] reads them.
+pub fn join_authors(credits: &[Credit]) -> String {
+    credits
+        .iter()
+        .map(|credit| match credit.role {
+            AuthorRole::Author => credit.name.clone(),
+            role => format!("{} ({})", credit.name, role),
+        })
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Returns the same author string for every way of writing the same people.
+pub fn normalize_authors(text: &str) -> String {
+    join_authors(&split_authors(text))
+}
+
+/// Two people are the same when their keys are equal.
+///
+/// The key ignores case, spaces and punctuation, so "J.R.R. Tolkien" and
+/// "J. R. R. Tolkien" are the same person.
+pub fn author_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// "Jay Rubin (translator)" becomes "Jay Rubin" and the translator role
+fn strip_role(part: &str) -> (&str, AuthorRole) {
+    part.strip_suffix(')')
+        .and_then(|part| part.rsplit_once('('))
+        .and_then(|(name, suffix)| Some((name.trim(), AuthorRole::from_suffix(suffix)?)))
+        .unwrap_or((part, AuthorRole::Author))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<String> {
+        split_authors(text)
+            .into_iter()
+            .map(|credit| credit.name)
+            .collect()
+    }
+
+    #[test]
+    fn last_name_first_is_turned_around() {
+        assert_eq!(names("Pratchett, Terry"), ["Terry Pratchett"]);
+        assert_eq!(names("Tolkien, J. R. R."), ["J. R. R. Tolkien"]);
+    }
+
+    #[test]
+    fn commas_between_full_names_separate_people() {
+        assert_eq!(
+            names("Terry Pratchett, Neil Gaiman"),
+            ["Terry Pratchett", "Neil Gaiman"]
+        );
+        assert_eq!(names("A. Author, B. Author, C. Author").len(), 3);
+    }
+
+    #[test]
+    fn people_are_split_and_keep_their_role() {
+        let credits = split_authors("Haruki Murakami & Jay Rubin (translator); Haruki  Murakami");
+        assert_eq!(
+            credits,
+            [
+                Credit {
+                    name: "Haruki Murakami".to_string(),
+                    role: AuthorRole::Author,
+                },
+                Credit {
+                    name: "Jay Rubin".to_string(),
+                    role: AuthorRole::Translator,
+                },
+            ]
+        );
+        assert_eq!(
+            normalize_authors("Jay Rubin (tr.) and Haruki Murakami"),
+            "Jay Rubin (translator) & Haruki Murakami"
+        );
+    }
+
+    #[test]
+    fn keys_ignore_case_and_punctuation() {
+        assert_eq!(author_key("J.R.R. Tolkien"), author_key("j. r. r. tolkien"));
+    }
+}
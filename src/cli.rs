@@ -0,0 +1,973 @@
+use crate::config::Config;
+use crate::database::models::{Author, Book, Quote, ReadingDay, ReadingSession, Series, Shelf};
+use crate::database::Library;
+use crate::editions::isbn13_to_isbn10;
+use crate::error::{BookyError, BookyResult};
+use crate::form::{display_date, BookForm, SessionForm};
+use crate::import::csv::CsvOptions;
+use crate::series::{format_index, format_series};
+use crate::tags::{join_tags, TagFilter, TagMatch};
+use crate::{export, import};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+/// booky is a minimalistic TUI tool for managing your growing book collection.
+///
+/// Running booky without a subcommand opens the TUI.
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Path to the database, overrides BOOKY_DB and the config file.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage the booky database.
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// List and merge authors.
+    Author {
+        #[command(subcommand)]
+        command: AuthorCommand,
+    },
+    /// List all tags.
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+    /// Manage shelves, saved tag filters like "book club".
+    Shelf {
+        #[command(subcommand)]
+        command: ShelfCommand,
+    },
+    /// Show series and which volume to read next.
+    Series {
+        #[command(subcommand)]
+        command: SeriesCommand,
+    },
+    /// Manage the reading sessions of a book.
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+    /// List, restore and purge deleted books.
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+    /// Add a book.
+    Add {
+        #[arg(long)]
+        title: String,
+        #[command(flatten)]
+        fields: BookArgs,
+    },
+    /// List all books, or only those with some tags.
+    #[command(alias = "ls")]
+    List {
+        /// Only list books with this tag, can be given several times.
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Books need every --tag instead of any of them.
+        #[arg(long, requires = "tags")]
+        all: bool,
+        /// Only list the books on this shelf.
+        #[arg(long, conflicts_with = "tags")]
+        shelf: Option<String>,
+    },
+    /// Show a single book.
+    Show { id: i32 },
+    /// Change the given fields of a book.
+    Edit {
+        id: i32,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: BookArgs,
+    },
+    /// Move a book to the trash.
+    #[command(alias = "delete")]
+    Rm { id: i32 },
+    /// Export all books.
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// File to write to, defaults to stdout.
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Import books from a file.
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Take back the latest change to the books.
+    Undo,
+    /// Make the latest undone change again.
+    Redo,
+    /// List the latest changes, the next one to undo first.
+    History {
+        /// How many changes to list.
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: i64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// A versioned JSON document that `booky import json` reads back.
+    Json,
+    /// One row per book with a header row.
+    Csv,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportSource {
+    /// A JSON file created by `booky export --format json`.
+    Json { path: PathBuf },
+    /// A CSV file with a header row, like a spreadsheet.
+    ///
+    /// Columns named title, author, tags, rating, status, start_date
+    /// or end_date are used automatically, use --map for other names.
+    Csv {
+        path: PathBuf,
+        /// Maps a column onto a field, for example --map "Date Read=end_date".
+        #[arg(long = "map", value_name = "COLUMN=FIELD")]
+        mappings: Vec<String>,
+        #[arg(long, default_value_t = ',')]
+        delimiter: char,
+        /// Check every row without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// The library export from Goodreads, goodreads_library_export.csv.
+    ///
+    /// Exclusive shelves become statuses and custom shelves become tags.
+    Goodreads {
+        path: PathBuf,
+        /// Check every row without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// A Calibre library, the folder that contains metadata.db.
+    ///
+    /// Books are to-read unless they have a "read" tag or yes/no column.
+    Calibre {
+        path: PathBuf,
+        /// Check every book without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Highlights from a Kindle's "My Clippings.txt", they are saved as quotes.
+    ///
+    /// Highlights go to the book with a similar title and author,
+    /// books that aren't in the library yet are added.
+    Kindle {
+        path: PathBuf,
+        /// Check every clipping without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Reading time and progress from KOReader.
+    ///
+    /// Give it statistics.sqlite3, metadata.*.lua sidecars or folders to
+    /// search for both, like the root of your e-reader. Only books that are
+    /// already in the library are updated.
+    #[command(name = "koreader")]
+    KoReader {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Check everything without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// The CSV export from StoryGraph.
+    #[command(name = "storygraph")]
+    StoryGraph {
+        path: PathBuf,
+        /// Check every row without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// The tab separated export from LibraryThing.
+    #[command(name = "librarything")]
+    LibraryThing {
+        path: PathBuf,
+        /// Check every row without saving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Apply all pending migrations.
+    Migrate,
+    /// Show the schema version and any pending migrations.
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthorCommand {
+    /// List every author with how many of their books you have and have read.
+    #[command(alias = "ls")]
+    List,
+    /// Move all books of an author to another one and delete the first.
+    ///
+    /// Use it when the same person was added under two names.
+    Merge {
+        /// Id of the author that is removed.
+        from: i32,
+        /// Id of the author that gets the books.
+        into: i32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TagCommand {
+    /// List every tag with how many books have it.
+    #[command(alias = "ls")]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ShelfCommand {
+    /// List every shelf with its tags.
+    #[command(alias = "ls")]
+    List,
+    /// Save a shelf that holds the books with any of its tags.
+    Add {
+        name: String,
+        /// A tag of the shelf, can be given several times.
+        #[arg(long = "tag", value_name = "TAG", required = true)]
+        tags: Vec<String>,
+        /// Books need every tag instead of any of them.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Delete a shelf, its books are kept.
+    #[command(alias = "delete")]
+    Rm { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SeriesCommand {
+    /// List every series with how many volumes were read and the next one.
+    #[command(alias = "ls")]
+    List,
+    /// List the volumes of a series in order.
+    Show { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrashCommand {
+    /// List the books in the trash, the last deleted one first.
+    #[command(alias = "ls")]
+    List,
+    /// Take a book out of the trash.
+    Restore { id: i32 },
+    /// Delete a book in the trash for good.
+    Purge { id: i32 },
+    /// Delete every book in the trash for good.
+    Empty,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionCommand {
+    /// List every time a book was read.
+    #[command(alias = "ls")]
+    List { id: i32 },
+    /// Record another time a book was read, like a reread.
+    Add {
+        id: i32,
+        /// Date you started reading, like 2023-04-12, 2023-04, 2023 or today.
+        #[arg(long, value_name = "DATE")]
+        start: Option<String>,
+        /// Date you finished or stopped reading, like 2023-04-12, 2023 or yesterday.
+        #[arg(long, value_name = "DATE")]
+        end: Option<String>,
+        /// You stopped reading before the end.
+        #[arg(long)]
+        abandoned: bool,
+        /// Pages read in this session.
+        #[arg(long)]
+        pages: Option<String>,
+        /// Minutes spent reading in this session.
+        #[arg(long)]
+        minutes: Option<String>,
+    },
+}
+
+/// Book fields that can be given on the command line.
+///
+/// They are validated the same way as the add/update popup, see [`BookForm`].
+#[derive(Debug, Args)]
+pub struct BookArgs {
+    /// Separate several people with &, add (translator) or (editor) after a name.
+    #[arg(long)]
+    pub author: Option<String>,
+    /// Separate several tags with commas, like "fantasy, book club".
+    #[arg(long, alias = "genre")]
+    pub tags: Option<String>,
+    #[arg(long)]
+    pub rating: Option<String>,
+    /// want-to-read, reading, paused, finished or abandoned.
+    #[arg(long)]
+    pub status: Option<String>,
+    /// Series the book is a volume of, an empty value removes it.
+    #[arg(long)]
+    pub series: Option<String>,
+    /// Number of the volume in its series, like 3 or 2.5.
+    #[arg(long, value_name = "NUMBER")]
+    pub volume: Option<String>,
+    /// ISBN-10 or ISBN-13, it is saved as ISBN-13.
+    #[arg(long)]
+    pub isbn: Option<String>,
+    #[arg(long)]
+    pub publisher: Option<String>,
+    /// Year the edition was published.
+    #[arg(long)]
+    pub year: Option<String>,
+    /// Page count of the edition.
+    #[arg(long)]
+    pub pages: Option<String>,
+    #[arg(long)]
+    pub language: Option<String>,
+    /// hardcover, paperback, ebook or audiobook.
+    #[arg(long)]
+    pub format: Option<String>,
+    /// Date you started reading, like 2023-04-12, 2023-04, 2023 or today.
+    #[arg(long, value_name = "DATE")]
+    pub start: Option<String>,
+    /// Date you finished reading, like 2023-04-12, 2023 or yesterday.
+    #[arg(long, value_name = "DATE")]
+    pub end: Option<String>,
+}
+
+impl BookArgs {
+    /// Overwrites the fields of `form` that were given on the command line.
+    pub fn apply(self, form: &mut BookForm) {
+        let fields = [
+            (self.author, &mut form.author),
+            (self.tags, &mut form.tags),
+            (self.rating, &mut form.rating),
+            (self.status, &mut form.status),
+            (self.series, &mut form.series),
+            (self.volume, &mut form.series_index),
+            (self.isbn, &mut form.isbn),
+            (self.publisher, &mut form.publisher),
+            (self.year, &mut form.published_year),
+            (self.pages, &mut form.pages),
+            (self.language, &mut form.language),
+            (self.format, &mut form.format),
+            (self.start, &mut form.start_date),
+            (self.end, &mut form.end_date),
+        ];
+        for (value, field) in fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+    }
+}
+
+/// Runs a subcommand against `library`, printing the results to stdout.
+pub fn run(command: Command, library: &mut Library, config: &Config) -> BookyResult<()> {
+    match command {
+        Command::Db { command } => run_db_command(command, library),
+        Command::Author { command } => run_author_command(command, library),
+        Command::Tag { command } => run_tag_command(command, library),
+        Command::Shelf { command } => run_shelf_command(command, library),
+        Command::Series { command } => run_series_command(command, library),
+        Command::Session { command } => run_session_command(command, library, config),
+        Command::Trash { command } => run_trash_command(command, library),
+        Command::Add { title, fields } => {
+            let mut form = BookForm {
+                title,
+                ..BookForm::default()
+            };
+            fields.apply(&mut form);
+            let book = library.create_book(&form.to_new_book(config.date_order)?)?;
+            println!("Added book {}: {}", book.id, book.title);
+            Ok(())
+        }
+        Command::List { tags, all, shelf } => {
+            let tag_filter = match shelf {
+                Some(name) => {
+                    let shelf = find_shelf(library, &name)?;
+                    library.shelf_filter(&shelf)?
+                }
+                None => TagFilter::new(&join_tags(&tags), tag_match(all)),
+            };
+            print_books(&library.tagged_books(&tag_filter)?);
+            Ok(())
+        }
+        Command::Show { id } => {
+            print_book(&find_book(library, id)?);
+            print_sessions(&library.sessions(id)?);
+            print_reading_log(&library.reading_log(id)?);
+            print_quotes(&library.quotes(id)?);
+            Ok(())
+        }
+        Command::Edit { id, title, fields } => {
+            let mut form = BookForm::from_book(&find_book(library, id)?);
+            if let Some(title) = title {
+                form.title = title;
+            }
+            fields.apply(&mut form);
+            let book = library.update_book(id, &form.to_new_book(config.date_order)?)?;
+            println!("Updated book {}: {}", book.id, book.title);
+            Ok(())
+        }
+        Command::Rm { id } => {
+            let book = find_book(library, id)?;
+            library.delete_book(id)?;
+            println!(
+                "Moved book {} to the trash: {}, `booky trash restore {}` brings it back",
+                book.id, book.title, book.id
+            );
+            Ok(())
+        }
+        Command::Export { format, output } => {
+            let books = library.books()?;
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            match format {
+                ExportFormat::Json => export::write_json(
+                    books,
+                    library.all_sessions()?,
+                    library.all_quotes()?,
+                    library.full_reading_log()?,
+                    writer,
+                ),
+                ExportFormat::Csv => export::write_csv(books, writer),
+            }
+        }
+        Command::Import { source } => {
+            let report = match source {
+                ImportSource::Json { path } => {
+                    import::json::import(library, BufReader::new(File::open(path)?))?.to_string()
+                }
+                ImportSource::Csv {
+                    path,
+                    mappings,
+                    delimiter,
+                    dry_run,
+                } => {
+                    let mut options = CsvOptions {
+                        delimiter: single_byte(delimiter)?,
+                        dry_run,
+                        date_order: config.date_order,
+                        ..CsvOptions::default()
+                    };
+                    for pair in &mappings {
+                        options.mapping.map_pair(pair)?;
+                    }
+                    print_dry_run(dry_run);
+                    import::csv::import(library, File::open(path)?, &options)?.to_string()
+                }
+                ImportSource::Goodreads { path, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::goodreads::import(library, File::open(path)?, dry_run)?.to_string()
+                }
+                ImportSource::Calibre { path, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::calibre::import(library, &path, dry_run)?.to_string()
+                }
+                ImportSource::Kindle { path, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::kindle::import(library, File::open(path)?, dry_run)?.to_string()
+                }
+                ImportSource::KoReader { paths, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::koreader::import(library, &paths, dry_run)?.to_string()
+                }
+                ImportSource::StoryGraph { path, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::storygraph::import(library, File::open(path)?, dry_run)?.to_string()
+                }
+                ImportSource::LibraryThing { path, dry_run } => {
+                    print_dry_run(dry_run);
+                    import::librarything::import(library, File::open(path)?, dry_run)?.to_string()
+                }
+            };
+            println!("{}", report);
+            Ok(())
+        }
+        Command::Undo => {
+            match library.undo()? {
+                Some(entry) => println!("Undid: {}", entry.description),
+                None => println!("Nothing to undo"),
+            }
+            Ok(())
+        }
+        Command::Redo => {
+            match library.redo()? {
+                Some(entry) => println!("Redid: {}", entry.description),
+                None => println!("Nothing to redo"),
+            }
+            Ok(())
+        }
+        Command::History { limit } => {
+            for entry in library.history(limit)? {
+                let undone = if entry.undone { "  (undone)" } else { "" };
+                println!(
+                    "{}  {}  {}{}",
+                    entry.id,
+                    entry.created_at.format("%Y-%m-%d %H:%M"),
+                    entry.description,
+                    undone
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_db_command(command: DbCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        DbCommand::Migrate => {
+            let applied = library.run_migrations()?;
+            if applied.is_empty() {
+                println!("No pending migrations");
+            }
+            for version in &applied {
+                println!("Applied migration {version}");
+            }
+        }
+        DbCommand::Status => {
+            let pending = library.pending_migrations()?;
+            for version in &pending {
+                println!("Pending migration {version}");
+            }
+        }
+    }
+
+    match library.schema_version()? {
+        Some(version) => println!("Schema version: {version}"),
+        None => println!("Schema version: none (database is empty)"),
+    }
+    Ok(())
+}
+
+fn run_author_command(command: AuthorCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        AuthorCommand::List => {
+            let authors = library.authors()?;
+            let width = authors
+                .iter()
+                .map(|author| author.name.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max("Name".len());
+            println!(
+                "{:<4}  {:<width$}  Books  Read",
+                "Id",
+                "Name",
+                width = width
+            );
+            for author in &authors {
+                println!(
+                    "{:<4}  {:<width$}  {:<5}  {}",
+                    author.id,
+                    author.name,
+                    author.books,
+                    author.read,
+                    width = width
+                );
+            }
+        }
+        AuthorCommand::Merge { from, into } => {
+            let from = find_author(library, from)?;
+            let into = find_author(library, into)?;
+            let moved = library.merge_authors(from.id, into.id)?;
+            println!(
+                "Merged {} into {}, moved {} book(s)",
+                from.name, into.name, moved
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_tag_command(command: TagCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        TagCommand::List => {
+            let tags = library.tags()?;
+            let width = tags
+                .iter()
+                .map(|tag| tag.name.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max("Name".len());
+            println!("{:<width$}  Books", "Name", width = width);
+            for tag in &tags {
+                println!("{:<width$}  {}", tag.name, tag.books, width = width);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_trash_command(command: TrashCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        TrashCommand::List => {
+            for book in library.trash()? {
+                let author = match book.author.as_str() {
+                    "" => String::new(),
+                    author => format!(" by {}", author),
+                };
+                println!(
+                    "{}  deleted {}  {}{}",
+                    book.id,
+                    book.deleted_at.format("%Y-%m-%d %H:%M"),
+                    book.title,
+                    author
+                );
+            }
+        }
+        TrashCommand::Restore { id } => {
+            if !library.trash()?.iter().any(|book| book.id == id) {
+                return Err(not_in_trash(id));
+            }
+            let book = library.restore_book(id)?;
+            println!("Restored book {}: {}", book.id, book.title);
+        }
+        TrashCommand::Purge { id } => {
+            if library.purge_books(&[id])? == 0 {
+                return Err(not_in_trash(id));
+            }
+            println!("Purged book {}", id);
+        }
+        TrashCommand::Empty => {
+            let ids: Vec<i32> = library.trash()?.iter().map(|book| book.id).collect();
+            let purged = library.purge_books(&ids)?;
+            println!("Purged {} book(s)", purged);
+        }
+    }
+    Ok(())
+}
+
+fn not_in_trash(id: i32) -> BookyError {
+    BookyError::Validation(format!("There is no book with id {} in the trash", id))
+}
+
+fn run_shelf_command(command: ShelfCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        ShelfCommand::List => {
+            for shelf in library.shelves()? {
+                let tag_filter = library.shelf_filter(&shelf)?;
+                let books = library.tagged_books(&tag_filter)?.len();
+                let mode = match tag_filter.mode {
+                    TagMatch::Any => "any of",
+                    TagMatch::All => "all of",
+                };
+                println!(
+                    "{}: {} book(s) with {} {}",
+                    shelf.name,
+                    books,
+                    mode,
+                    join_tags(&tag_filter.tags)
+                );
+            }
+        }
+        ShelfCommand::Add { name, tags, all } => {
+            if name.trim().is_empty() {
+                return Err(BookyError::Validation("A shelf needs a name".to_string()));
+            }
+            if library.shelf(&name)?.is_some() {
+                return Err(BookyError::Validation(format!(
+                    "There already is a shelf called \"{}\"",
+                    name
+                )));
+            }
+            let tag_filter = TagFilter::new(&join_tags(&tags), tag_match(all));
+            if tag_filter.is_empty() {
+                return Err(BookyError::Validation(
+                    "A shelf needs at least one tag".to_string(),
+                ));
+            }
+            let shelf = library.create_shelf(&name, &tag_filter)?;
+            println!("Added shelf {}", shelf.name);
+        }
+        ShelfCommand::Rm { name } => {
+            let shelf = find_shelf(library, &name)?;
+            library.delete_shelf(shelf.id)?;
+            println!("Deleted shelf {}", shelf.name);
+        }
+    }
+    Ok(())
+}
+
+fn run_series_command(command: SeriesCommand, library: &mut Library) -> BookyResult<()> {
+    match command {
+        SeriesCommand::List => {
+            for series in library.series_list()? {
+                let next = match library.next_unread(series.id)? {
+                    Some(book) => format!(", next: {}", volume_title(&book)),
+                    None => String::new(),
+                };
+                println!(
+                    "{}: read {} of {}{}",
+                    series.name, series.read, series.volumes, next
+                );
+            }
+        }
+        SeriesCommand::Show { name } => {
+            let series = find_series(library, &name)?;
+            let next = library.next_unread(series.id)?.map(|book| book.id);
+            println!("{}", series.name);
+            for book in library.series_books(series.id)? {
+                let state = if book.is_read() {
+                    "read"
+                } else if Some(book.id) == next {
+                    "next"
+                } else {
+                    ""
+                };
+                println!("  {:<4}  {}  {}", state, book.id, volume_title(&book));
+            }
+        }
+    }
+    Ok(())
+}
+
+// "#3 Oathbringer", or just the title for volumes without a number
+fn volume_title(book: &Book) -> String {
+    match book.series_index {
+        Some(index) => format!("#{} {}", format_index(index), book.title),
+        None => book.title.clone(),
+    }
+}
+
+fn tag_match(all: bool) -> TagMatch {
+    if all {
+        TagMatch::All
+    } else {
+        TagMatch::Any
+    }
+}
+
+fn run_session_command(
+    command: SessionCommand,
+    library: &mut Library,
+    config: &Config,
+) -> BookyResult<()> {
+    match command {
+        SessionCommand::List { id } => {
+            let book = find_book(library, id)?;
+            println!("{}", book.title);
+            print_sessions(&library.sessions(id)?);
+        }
+        SessionCommand::Add {
+            id,
+            start,
+            end,
+            abandoned,
+            pages,
+            minutes,
+        } => {
+            let book = find_book(library, id)?;
+            let form = SessionForm {
+                started: start.unwrap_or_default(),
+                finished: end.unwrap_or_default(),
+                abandoned,
+                pages: pages.unwrap_or_default(),
+                minutes: minutes.unwrap_or_default(),
+            };
+            library.create_session(&form.to_new_session(id, config.date_order)?)?;
+            println!(
+                "Added a reading session to book {}: {}",
+                book.id, book.title
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_dry_run(dry_run: bool) {
+    if dry_run {
+        println!("Dry run, nothing will be saved");
+    }
+}
+
+fn single_byte(delimiter: char) -> BookyResult<u8> {
+    u8::try_from(delimiter)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| {
+            BookyError::Validation(format!("Delimiter must be ascii, got {}", delimiter))
+        })
+}
+
+fn find_book(library: &mut Library, id: i32) -> BookyResult<Book> {
+    library
+        .book(id)?
+        .ok_or_else(|| BookyError::Validation(format!("There is no book with id {}", id)))
+}
+
+fn find_author(library: &mut Library, id: i32) -> BookyResult<Author> {
+    library
+        .author(id)?
+        .ok_or_else(|| BookyError::Validation(format!("There is no author with id {}", id)))
+}
+
+fn find_shelf(library: &mut Library, name: &str) -> BookyResult<Shelf> {
+    library
+        .shelf(name)?
+        .ok_or_else(|| BookyError::Validation(format!("There is no shelf called \"{}\"", name)))
+}
+
+fn find_series(library: &mut Library, name: &str) -> BookyResult<Series> {
+    library
+        .series_by_name(name)?
+        .ok_or_else(|| BookyError::Validation(format!("There is no series called \"{}\"", name)))
+}
+
+fn print_books(books: &[Book]) {
+    let headers = [
+        "Id",
+        "Title",
+        "Author",
+        "Tags",
+        "Rating",
+        "Status",
+        "Series",
+        "StartDate",
+        "EndDate",
+    ];
+    let rows: Vec<[String; 9]> = books
+        .iter()
+        .map(|book| {
+            [
+                book.id.to_string(),
+                book.title.clone(),
+                book.author.clone(),
+                book.tags.clone(),
+                book.rating.to_string(),
+                book.status.to_string(),
+                format_series(book.series.as_deref(), book.series_index),
+                display_date(book.start()),
+                display_date(book.end()),
+            ]
+        })
+        .collect();
+
+    // Every column is as wide as its widest value
+    let mut widths = headers.map(|header| header.chars().count());
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let print_row = |values: &[&str]| {
+        let line: Vec<String> = values
+            .iter()
+            .zip(widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+
+    print_row(&headers);
+    for row in &rows {
+        print_row(&row.each_ref().map(String::as_str));
+    }
+}
+
+fn print_book(book: &Book) {
+    println!("Id:        {}", book.id);
+    println!("Title:     {}", book.title);
+    println!("Author:    {}", book.author);
+    println!("Tags:      {}", book.tags);
+    println!("Rating:    {}", book.rating);
+    println!("Status:    {}", book.status);
+    println!(
+        "Series:    {}",
+        format_series(book.series.as_deref(), book.series_index)
+    );
+    let isbn = book.isbn.as_deref().unwrap_or_default();
+    match isbn13_to_isbn10(isbn) {
+        Some(isbn10) => println!("ISBN:      {} (ISBN-10 {})", isbn, isbn10),
+        None => println!("ISBN:      {}", isbn),
+    }
+    println!(
+        "Publisher: {}",
+        book.publisher.as_deref().unwrap_or_default()
+    );
+    println!(
+        "Year:      {}",
+        book.published_year
+            .map(|year| year.to_string())
+            .unwrap_or_default()
+    );
+    println!(
+        "Pages:     {}",
+        book.pages
+            .map(|pages| pages.to_string())
+            .unwrap_or_default()
+    );
+    println!(
+        "Language:  {}",
+        book.language.as_deref().unwrap_or_default()
+    );
+    println!("Format:    {}", book.format.as_deref().unwrap_or_default());
+    println!("StartDate: {}", display_date(book.start()));
+    println!("EndDate:   {}", display_date(book.end()));
+    println!("Rereads:   {}", book.rereads());
+}
+
+fn print_sessions(sessions: &[ReadingSession]) {
+    for session in sessions {
+        let mut line = format!(
+            "  {} to {}, {}",
+            display_date(session.start()),
+            display_date(session.end()),
+            session.outcome.as_deref().unwrap_or("reading")
+        );
+        if let Some(pages) = session.pages {
+            line.push_str(&format!(", {} page(s)", pages));
+        }
+        if let Some(minutes) = session.minutes {
+            line.push_str(&format!(", {} minute(s)", minutes));
+        }
+        println!("{}", line);
+    }
+}
+
+fn print_reading_log(reading_log: &[ReadingDay]) {
+    if reading_log.is_empty() {
+        return;
+    }
+    let seconds: i32 = reading_log.iter().map(|day| day.seconds).sum();
+    let pages: i32 = reading_log.iter().map(|day| day.pages).sum();
+    println!(
+        "Reading:   {}h {:02}m over {} day(s), {} page(s)",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        reading_log.len(),
+        pages
+    );
+}
+
+fn print_quotes(quotes: &[Quote]) {
+    for quote in quotes {
+        println!();
+        println!("{}", quote.text);
+        if let Some(location) = &quote.location {
+            println!("  -- {}", location);
+        }
+    }
+}
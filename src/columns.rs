@@ -0,0 +1,126 @@
+use crate::database::models::Book;
+use crate::dates::DatePrecision;
+use crate::form::display_date;
+use crate::series::format_series;
+use crate::status::ReadingStatus;
+use serde::Deserialize;
+
+/// A column of the book table in the tui.
+///
+/// The `columns` key of the config file picks the columns and their order,
+/// for example `columns = ["title", "author", "isbn", "pages", "status"]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Id,
+    Title,
+    Author,
+    Tags,
+    Rating,
+    Status,
+    Series,
+    Isbn,
+    Publisher,
+    Year,
+    Pages,
+    Language,
+    Format,
+    StartDate,
+    EndDate,
+    /// Days it took to read the book, empty until it is finished on a known day.
+    Days,
+    Rereads,
+}
+
+impl Column {
+    /// The columns shown when the config file doesn't pick any.
+    pub const DEFAULT: [Column; 11] = [
+        Column::Id,
+        Column::Title,
+        Column::Author,
+        Column::Tags,
+        Column::Rating,
+        Column::Status,
+        Column::Series,
+        Column::StartDate,
+        Column::EndDate,
+        Column::Days,
+        Column::Rereads,
+    ];
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Id => "Id",
+            Column::Title => "Title",
+            Column::Author => "Author",
+            Column::Tags => "Tags",
+            Column::Rating => "Rating",
+            Column::Status => "Status",
+            Column::Series => "Series",
+            Column::Isbn => "ISBN",
+            Column::Publisher => "Publisher",
+            Column::Year => "Year",
+            Column::Pages => "Pages",
+            Column::Language => "Language",
+            Column::Format => "Format",
+            Column::StartDate => "StartDate",
+            Column::EndDate => "EndDate",
+            Column::Days => "Finished (days)",
+            Column::Rereads => "Rereads",
+        }
+    }
+
+    /// Width of the column in characters.
+    pub fn width(self) -> u16 {
+        match self {
+            Column::Id => 2,
+            Column::Title | Column::Author | Column::Series | Column::Publisher => 20,
+            Column::Isbn => 13,
+            Column::Days => 15,
+            Column::Year | Column::Pages | Column::Rereads => 7,
+            Column::Status => 12,
+            _ => 10,
+        }
+    }
+
+    /// The value of this column for `book`, missing values are empty.
+    pub fn value(self, book: &Book) -> String {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<i32>| value.map(|n| n.to_string()).unwrap_or_default();
+
+        match self {
+            Column::Id => book.id.to_string(),
+            Column::Title => book.title.clone(),
+            Column::Author => book.author.clone(),
+            Column::Tags => book.tags.clone(),
+            Column::Rating => book.rating.to_string(),
+            Column::Status => book.status.to_string(),
+            Column::Series => format_series(book.series.as_deref(), book.series_index),
+            Column::Isbn => text(&book.isbn),
+            Column::Publisher => text(&book.publisher),
+            Column::Year => number(book.published_year),
+            Column::Pages => number(book.pages),
+            Column::Language => text(&book.language),
+            Column::Format => text(&book.format),
+            Column::StartDate => display_date(book.start()),
+            Column::EndDate => display_date(book.end()),
+            // Only finished books with both days known took a number of days
+            Column::Days => match (book.status, book.start(), book.end()) {
+                (ReadingStatus::Finished, Some(start), Some(end))
+                    if start.precision == DatePrecision::Day
+                        && end.precision == DatePrecision::Day =>
+                {
+                    end.date
+                        .signed_duration_since(start.date)
+                        .num_days()
+                        .to_string()
+                }
+                _ => String::new(),
+            },
+            Column::Rereads => match book.rereads() {
+                0 => String::new(),
+                rereads => rereads.to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::columns::Column;
+use crate::dates::DateOrder;
+use crate::error::{BookyError, BookyResult};
+use dirs_2::{config_dir, data_dir, document_dir, home_dir};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Environment variable that overrides the database location.
+pub const DB_ENV_VAR: &str = "BOOKY_DB";
+
+/// Days deleted books stay in the trash when the config file doesn't say.
+pub const DEFAULT_TRASH_DAYS: u32 = 30;
+
+/// User configuration, read from `config.toml` in the booky config directory.
+///
+/// Every field is optional, a missing config file is the same as an empty one.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Path to the sqlite database.
+    pub db: Option<PathBuf>,
+    /// Columns of the book table in the tui, see [`Column`].
+    pub columns: Option<Vec<Column>>,
+    /// How dates like `12/04/2023` are read, `"day-month"` or `"month-day"`.
+    pub date_order: DateOrder,
+    /// Days deleted books stay in the trash, 0 keeps them until they are purged.
+    pub trash_days: Option<u32>,
+}
+
+impl Config {
+    /// Location of the config file, `$XDG_CONFIG_HOME/booky/config.toml` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("booky").join("config.toml"))
+    }
+
+    /// Loads the config file if there is one.
+    pub fn load() -> BookyResult<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> BookyResult<Self> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| BookyError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The columns of the book table, [`Column::DEFAULT`] unless the config picks them.
+    pub fn columns(&self) -> Vec<Column> {
+        match &self.columns {
+            Some(columns) if !columns.is_empty() => columns.clone(),
+            _ => Column::DEFAULT.to_vec(),
+        }
+    }
+
+    /// How long deleted books stay in the trash, `None` when they are never purged.
+    pub fn trash_retention(&self) -> Option<chrono::Duration> {
+        match self.trash_days.unwrap_or(DEFAULT_TRASH_DAYS) {
+            0 => None,
+            days => Some(chrono::Duration::days(days.into())),
+        }
+    }
+
+    /// Resolves the database path.
+    ///
+    /// The first one that is set wins: the `--db` argument, the `BOOKY_DB`
+    /// environment variable, the config file and finally [`default_db_path`].
+    pub fn db_path(&self, cli_db: Option<PathBuf>) -> BookyResult<PathBuf> {
+        let path = cli_db
+            .or_else(|| env::var_os(DB_ENV_VAR).map(PathBuf::from))
+            .or_else(|| self.db.clone());
+
+        match path {
+            Some(path) => Ok(expand_home(path)),
+            None => default_db_path(),
+        }
+    }
+}
+
+/// `$XDG_DATA_HOME/booky/books.db` on Linux, the platform data directory elsewhere.
+///
+/// Older versions of booky kept the database in `~/Documents/booky`, that
+/// file is used as long as there is none in the data directory yet.
+pub fn default_db_path() -> BookyResult<PathBuf> {
+    let path = data_db_path()?;
+    match legacy_db_path() {
+        Some(legacy) if !path.exists() && legacy.exists() => Ok(legacy),
+        _ => Ok(path),
+    }
+}
+
+/// Where new databases are created, see [`default_db_path`].
+pub fn data_db_path() -> BookyResult<PathBuf> {
+    let dir = data_dir().ok_or_else(|| {
+        BookyError::Config("Could not find a data directory, use --db or BOOKY_DB".to_string())
+    })?;
+    Ok(dir.join("booky").join("books.db"))
+}
+
+/// Where booky kept its database before it used the data directory.
+pub fn legacy_db_path() -> Option<PathBuf> {
+    document_dir().map(|dir| dir.join("booky").join("books.db"))
+}
+
+// Config files are written by hand, so allow ~/ in paths
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path,
+    }
+}
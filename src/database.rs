@@ -1,99 +1,1098 @@
+pub mod history;
+pub mod library;
 pub mod models;
 pub mod schema;
 
-use crate::app::App;
+pub use library::Library;
+
+use crate::authors::{author_key, split_authors};
 use crate::database::models::*;
-use crate::database::schema::books::dsl::books;
-use crate::database::schema::books::*;
+use crate::database::schema::book_overview;
+use crate::error::{BookyError, BookyResult};
+use crate::series::split_series_title;
+use crate::tags::{split_tags, TagFilter, TagMatch};
 use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use dirs_2::document_dir;
+use std::path::Path;
+
+// Migrations are compiled into the binary so booky can create
+// its database no matter where it is launched from
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+pub fn establish_connection(path: &Path) -> BookyResult<SqliteConnection> {
+    // Sqlite will automatically create books.db if it does't exist
+    let mut connection = SqliteConnection::establish(&path.display().to_string())?;
+    // Foreign keys are off by default, without them quotes outlive their book
+    diesel::sql_query("PRAGMA foreign_keys = ON").execute(&mut connection)?;
+    Ok(connection)
+}
+
+/// An empty library that only lives in memory, for tests.
+#[cfg(test)]
+pub(crate) fn memory_library() -> Library {
+    let connection = establish_connection(Path::new(":memory:")).unwrap();
+    let mut library = Library::new(connection);
+    library.run_migrations().unwrap();
+    library
+}
 
-pub fn get_db_path() -> String {
-    document_dir()
-        .expect("Failed to find /Documents")
-        .join("booky")
-        .join("books.db")
+/// Opens a database of another application, sqlite never writes to it.
+pub fn establish_read_only_connection(path: &Path) -> BookyResult<SqliteConnection> {
+    // Open it as an uri, the characters that mean something in an uri are escaped
+    let uri = path
         .display()
         .to_string()
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    Ok(SqliteConnection::establish(&format!(
+        "file:{}?mode=ro",
+        uri
+    ))?)
 }
 
-pub fn establish_connection() -> SqliteConnection {
-    // Sqlite will automatically create books.db if it does't exist
-    let path = get_db_path();
-    let mut connection =
-        SqliteConnection::establish(&path).unwrap_or_else(|_| panic!("Error connecting to"));
-    connection
+/// Runs all pending migrations and returns the versions that were applied.
+pub fn run_migrations(connection: &mut SqliteConnection) -> BookyResult<Vec<String>> {
+    let applied = connection
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(BookyError::Migration)?;
+    let applied = applied.iter().map(|version| version.to_string()).collect();
+    // Some data can't be moved in sql, those migrations leave it to us
+    backfill_authors(connection)?;
+    backfill_tags(connection)?;
+    backfill_series(connection)?;
+    Ok(applied)
 }
 
-pub fn create_book(new_book: NewBook) -> Book {
-    use crate::database::schema::books;
-    let connection = &mut establish_connection();
+/// Returns the versions of all migrations that were already applied, oldest first.
+pub fn applied_migrations(connection: &mut SqliteConnection) -> BookyResult<Vec<String>> {
+    let mut applied: Vec<String> = connection
+        .applied_migrations()
+        .map_err(BookyError::Migration)?
+        .iter()
+        .map(|version| version.to_string())
+        .collect();
+    applied.sort();
+    Ok(applied)
+}
+
+/// Returns the versions of the embedded migrations that still have to be applied.
+pub fn pending_migrations(connection: &mut SqliteConnection) -> BookyResult<Vec<String>> {
+    let pending = connection
+        .pending_migrations(MIGRATIONS)
+        .map_err(BookyError::Migration)?;
+    Ok(pending
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .collect())
+}
+
+/// The schema version is the version of the latest applied migration.
+pub fn schema_version(connection: &mut SqliteConnection) -> BookyResult<Option<String>> {
+    Ok(applied_migrations(connection)?.pop())
+}
 
-    diesel::insert_into(books::table)
-        .values(&new_book)
-        .returning(Book::as_returning())
-        .get_result(connection)
-        .expect("Error saving new book")
+/// Adds a book, its dates become its first reading session.
+pub fn create_book(connection: &mut SqliteConnection, new_book: &NewBook) -> BookyResult<Book> {
+    connection.transaction(|connection| {
+        let book = insert_book(connection, None, new_book)?;
+        match NewReadingSession::from_dates(book.id, new_book) {
+            Some(session) => {
+                create_session(connection, &session)?;
+                find_book(connection, book.id)
+            }
+            None => Ok(book),
+        }
+    })
 }
 
-// Do this without app parameter later
-pub fn get_books(app: &mut App) -> Vec<Book> {
-    let connection = &mut establish_connection();
+/// Adds a book without any reading sessions, used to restore exports.
+///
+/// The book gets `book_id` as its id when it is given.
+pub fn insert_book(
+    connection: &mut SqliteConnection,
+    book_id: Option<i32>,
+    new_book: &NewBook,
+) -> BookyResult<Book> {
+    connection.transaction(|connection| {
+        let record = BookRecord {
+            id: book_id,
+            ..BookRecord::from(new_book)
+        };
+        let book_id = insert_record(connection, &record)?;
+        set_book_authors(connection, book_id, &new_book.author)?;
+        set_book_tags(connection, book_id, &new_book.tags)?;
+        set_book_series(
+            connection,
+            book_id,
+            new_book.series.as_deref(),
+            new_book.series_index,
+        )?;
+        find_book(connection, book_id)
+    })
+}
 
-    let results = books
+/// Inserts all books in a single transaction, either all of them are saved or none.
+pub fn create_books(
+    connection: &mut SqliteConnection,
+    new_books: &[NewBook],
+) -> BookyResult<Vec<Book>> {
+    connection.transaction(|connection| {
+        new_books
+            .iter()
+            .map(|new_book| create_book(connection, new_book))
+            .collect()
+    })
+}
+
+pub fn get_books(connection: &mut SqliteConnection) -> BookyResult<Vec<Book>> {
+    Ok(book_overview::table
+        .order(book_overview::id)
         .select(Book::as_select())
-        .load(connection)
-        .expect("Error loading books");
-    app.items = results.clone();
-    results
+        .load(connection)?)
 }
 
-pub fn update_book(book_id: i32, update_book: NewBook) {
-    let connection = &mut establish_connection();
+pub fn get_book(connection: &mut SqliteConnection, book_id: i32) -> BookyResult<Option<Book>> {
+    let book = book_overview::table
+        .find(book_id)
+        .select(Book::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(book)
+}
 
-    diesel::update(books.find(book_id))
-        .set(update_book)
-        .execute(connection)
-        .expect("Error updating book");
+/// Changes a book, its dates change the latest reading session.
+pub fn update_book(
+    connection: &mut SqliteConnection,
+    book_id: i32,
+    update_book: &NewBook,
+) -> BookyResult<Book> {
+    use crate::database::schema::{books, reading_sessions};
+
+    connection.transaction(|connection| {
+        // The id is set to itself, a NULL id would fail
+        let record = BookRecord {
+            id: Some(book_id),
+            ..BookRecord::from(update_book)
+        };
+        // Books in the trash have to be restored first
+        let updated = diesel::update(
+            books::table
+                .find(book_id)
+                .filter(books::deleted_at.is_null()),
+        )
+        .set(record)
+        .execute(connection)?;
+        if updated == 0 {
+            return Err(diesel::result::Error::NotFound.into());
+        }
+        set_book_authors(connection, book_id, &update_book.author)?;
+        delete_orphan_authors(connection)?;
+        set_book_tags(connection, book_id, &update_book.tags)?;
+        delete_orphan_tags(connection)?;
+        set_book_series(
+            connection,
+            book_id,
+            update_book.series.as_deref(),
+            update_book.series_index,
+        )?;
+        delete_orphan_series(connection)?;
+
+        match latest_session(connection, book_id)? {
+            Some(session) => {
+                let outcome = update_book.outcome().or(session.outcome);
+                diesel::update(reading_sessions::table.find(session.id))
+                    .set((
+                        reading_sessions::started.eq(update_book.start_date),
+                        reading_sessions::finished.eq(update_book.end_date),
+                        reading_sessions::started_precision.eq(update_book.start_precision),
+                        reading_sessions::finished_precision.eq(update_book.end_precision),
+                        reading_sessions::outcome.eq(outcome),
+                    ))
+                    .execute(connection)?;
+            }
+            None => {
+                if let Some(session) = NewReadingSession::from_dates(book_id, update_book) {
+                    create_session(connection, &session)?;
+                }
+            }
+        }
+        find_book(connection, book_id)
+    })
 }
 
-pub fn delete_book(app: &mut App) {
-    let connection = &mut establish_connection();
-    if let Some(selected) = app.state.selected() {
-        let current_id = app.items.get(selected).unwrap().id;
-        app.items.remove(selected);
-        diesel::delete(books.filter(id.eq(current_id)))
-            .execute(connection)
-            .expect("Failed to delete book");
+/// Moves a book to the trash, see [`delete_books`].
+pub fn delete_book(connection: &mut SqliteConnection, book_id: i32) -> BookyResult<()> {
+    delete_books(connection, &[book_id])?;
+    Ok(())
+}
 
-        if selected > 1 {
-            app.state.select(Some(selected - 1))
-        } else {
-            app.state.select(Some(0))
+/// Moves all given books to the trash and returns how many were moved.
+///
+/// Books in the trash keep their authors, tags and sessions, so
+/// [`restore_book`] gives them back as they were. [`purge_books`] deletes
+/// them for good.
+pub fn delete_books(connection: &mut SqliteConnection, book_ids: &[i32]) -> BookyResult<usize> {
+    use crate::database::schema::books;
+
+    let now = chrono::Local::now().naive_local();
+    let deleted = diesel::update(
+        books::table
+            .filter(books::id.eq_any(book_ids))
+            .filter(books::deleted_at.is_null()),
+    )
+    .set(books::deleted_at.eq(now))
+    .execute(connection)?;
+    Ok(deleted)
+}
+
+/// Returns the books in the trash, the last deleted one first.
+pub fn get_trash(connection: &mut SqliteConnection) -> BookyResult<Vec<DeletedBook>> {
+    let results = diesel::sql_query(
+        "SELECT b.id, b.title,
+            coalesce((SELECT group_concat(name, ' & ') FROM (
+                SELECT a.name || CASE ba.role WHEN 'author' THEN '' ELSE ' (' || ba.role || ')' END AS name
+                FROM book_authors ba JOIN authors a ON a.id = ba.author_id
+                WHERE ba.book_id = b.id
+                ORDER BY ba.position)), '') AS author,
+            b.deleted_at
+        FROM books b
+        WHERE b.deleted_at IS NOT NULL
+        ORDER BY b.deleted_at DESC, b.id DESC",
+    )
+    .load(connection)?;
+    Ok(results)
+}
+
+/// Takes a book out of the trash.
+pub fn restore_book(connection: &mut SqliteConnection, book_id: i32) -> BookyResult<Book> {
+    use crate::database::schema::books;
+
+    connection.transaction(|connection| {
+        let restored = diesel::update(
+            books::table
+                .find(book_id)
+                .filter(books::deleted_at.is_not_null()),
+        )
+        .set(books::deleted_at.eq(None::<chrono::NaiveDateTime>))
+        .execute(connection)?;
+        if restored == 0 {
+            return Err(diesel::result::Error::NotFound.into());
         }
-    }
+        find_book(connection, book_id)
+    })
 }
 
-pub fn search_book(book_info: NewBook) -> Vec<Book> {
-    let connection = &mut establish_connection();
+/// Deletes books in the trash for good and returns how many were removed.
+///
+/// Books that aren't in the trash are left alone.
+pub fn purge_books(connection: &mut SqliteConnection, book_ids: &[i32]) -> BookyResult<usize> {
+    use crate::database::schema::books;
+
+    connection.transaction(|connection| {
+        let purged = diesel::delete(
+            books::table
+                .filter(books::id.eq_any(book_ids))
+                .filter(books::deleted_at.is_not_null()),
+        )
+        .execute(connection)?;
+        delete_orphan_authors(connection)?;
+        delete_orphan_tags(connection)?;
+        delete_orphan_series(connection)?;
+        Ok(purged)
+    })
+}
 
+/// Returns the ids of the books that were moved to the trash before `cutoff`.
+pub fn get_deleted_before(
+    connection: &mut SqliteConnection,
+    cutoff: chrono::NaiveDateTime,
+) -> BookyResult<Vec<i32>> {
+    use crate::database::schema::books;
+
+    let book_ids = books::table
+        .filter(books::deleted_at.lt(cutoff))
+        .select(books::id)
+        .load(connection)?;
+    Ok(book_ids)
+}
+
+/// Purges the books that were moved to the trash before `cutoff`.
+pub fn purge_deleted_before(
+    connection: &mut SqliteConnection,
+    cutoff: chrono::NaiveDateTime,
+) -> BookyResult<usize> {
+    let expired = get_deleted_before(connection, cutoff)?;
+    purge_books(connection, &expired)
+}
+
+/// Finds the books that match every field of `book_info`.
+///
+/// The tags of `book_info` are split into single tags, a book needs any or
+/// all of them depending on `tag_match`. Dates are only compared when they
+/// are given, so books without dates are found unless a date is searched for.
+pub fn search_book(
+    connection: &mut SqliteConnection,
+    book_info: &BookQuery,
+    tag_match: TagMatch,
+) -> BookyResult<Vec<Book>> {
     // Find a better way to do this...
     let title_pattern = format!("%{}%", book_info.title);
     let author_pattern = format!("%{}%", book_info.author);
-    let genre_pattern = format!("%{}%", book_info.genre);
-    let status_pattern = format!("%{}%", book_info.status);
 
-    let results = books
+    let mut query = book_overview::table
+        .filter(book_overview::title.like(title_pattern))
+        .filter(book_overview::author.like(author_pattern))
+        .filter(book_overview::rating.ge(book_info.rating))
+        .order(book_overview::id)
+        .into_boxed();
+    if let Some(status) = book_info.status {
+        query = query.filter(book_overview::status.eq(status));
+    }
+    if let Some(start_date) = book_info.start_date {
+        query = query.filter(book_overview::start_date.ge(start_date));
+    }
+    if let Some(end_date) = book_info.end_date {
+        query = query.filter(book_overview::end_date.le(end_date));
+    }
+    let tag_filter = TagFilter::new(&book_info.tags, tag_match);
+    Ok(filter_tags(query, &tag_filter).load(connection)?)
+}
+
+/// Returns the books that match `tag_filter`, or every book when it is empty.
+pub fn get_tagged_books(
+    connection: &mut SqliteConnection,
+    tag_filter: &TagFilter,
+) -> BookyResult<Vec<Book>> {
+    let query = book_overview::table.order(book_overview::id).into_boxed();
+    Ok(filter_tags(query, tag_filter).load(connection)?)
+}
+
+fn filter_tags<'a>(
+    query: book_overview::BoxedQuery<'a, Sqlite>,
+    tag_filter: &TagFilter,
+) -> book_overview::BoxedQuery<'a, Sqlite> {
+    use crate::database::schema::{book_tags, tags};
+
+    // tags.name ignores case, so "Fantasy" finds "fantasy"
+    let tagged = |names: Vec<String>| {
+        book_tags::table
+            .inner_join(tags::table)
+            .filter(tags::name.eq_any(names))
+            .select(book_tags::book_id)
+    };
+    match tag_filter.mode {
+        _ if tag_filter.is_empty() => query,
+        TagMatch::Any => query.filter(book_overview::id.eq_any(tagged(tag_filter.tags.clone()))),
+        TagMatch::All => tag_filter.tags.iter().fold(query, |query, tag| {
+            query.filter(book_overview::id.eq_any(tagged(vec![tag.clone()])))
+        }),
+    }
+}
+
+/// Returns every reading session of a book, the latest one last.
+pub fn get_sessions(
+    connection: &mut SqliteConnection,
+    book: i32,
+) -> BookyResult<Vec<ReadingSession>> {
+    use crate::database::schema::reading_sessions;
+
+    let mut results: Vec<ReadingSession> = reading_sessions::table
+        .filter(reading_sessions::book_id.eq(book))
+        .select(ReadingSession::as_select())
+        .load(connection)?;
+    // Same order as book_overview uses to pick the latest session
+    results.sort_by_key(|session| (session.started.or(session.finished), session.id));
+    Ok(results)
+}
+
+/// Returns the sessions of all books that aren't in the trash, used by exports.
+pub fn get_all_sessions(connection: &mut SqliteConnection) -> BookyResult<Vec<ReadingSession>> {
+    use crate::database::schema::{books, reading_sessions};
+
+    let results = reading_sessions::table
+        .inner_join(books::table)
+        .filter(books::deleted_at.is_null())
+        .order(reading_sessions::id)
+        .select(ReadingSession::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+pub fn create_session(
+    connection: &mut SqliteConnection,
+    new_session: &NewReadingSession,
+) -> BookyResult<ReadingSession> {
+    use crate::database::schema::reading_sessions;
+
+    let session = diesel::insert_into(reading_sessions::table)
+        .values(new_session)
+        .returning(ReadingSession::as_returning())
+        .get_result(connection)?;
+    Ok(session)
+}
+
+/// Links a book to the people in an author string, replacing its old authors.
+///
+/// The string is split with [`split_authors`], people that are already
+/// known are reused so every author exists only once.
+pub fn set_book_authors(
+    connection: &mut SqliteConnection,
+    book: i32,
+    author_text: &str,
+) -> BookyResult<()> {
+    use crate::database::schema::book_authors;
+
+    diesel::delete(book_authors::table.filter(book_authors::book_id.eq(book)))
+        .execute(connection)?;
+    for (position, credit) in split_authors(author_text).iter().enumerate() {
+        let author_id = find_or_create_author(connection, &credit.name)?;
+        diesel::insert_into(book_authors::table)
+            .values(BookAuthor {
+                book_id: book,
+                author_id,
+                role: credit.role.as_str().to_string(),
+                position: position as i32,
+            })
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Returns every author with how many of their books are in the library and were read.
+pub fn get_authors(connection: &mut SqliteConnection) -> BookyResult<Vec<AuthorSummary>> {
+    let results = diesel::sql_query(
+        "SELECT a.id, a.name, count(DISTINCT ba.book_id) AS books,
+            count(DISTINCT CASE WHEN b.status = 'finished' THEN b.id END) AS read
+        FROM authors a
+        JOIN book_authors ba ON ba.author_id = a.id
+        JOIN books b ON b.id = ba.book_id
+        WHERE b.deleted_at IS NULL
+        GROUP BY a.id
+        ORDER BY a.name COLLATE NOCASE",
+    )
+    .load(connection)?;
+    Ok(results)
+}
+
+pub fn get_author(
+    connection: &mut SqliteConnection,
+    author_id: i32,
+) -> BookyResult<Option<Author>> {
+    use crate::database::schema::authors;
+
+    let found = authors::table
+        .find(author_id)
+        .select(Author::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(found)
+}
+
+/// Moves every book of the author `from` to `into` and deletes `from`.
+///
+/// Use it when the same person was added twice under different names.
+/// Returns how many books were moved.
+pub fn merge_authors(
+    connection: &mut SqliteConnection,
+    from: i32,
+    into: i32,
+) -> BookyResult<usize> {
+    use crate::database::schema::{authors, book_authors};
+
+    // Merging an author into themselves would take them off all their books
+    if from == into {
+        return Err(BookyError::Validation(
+            "Can't merge an author into themselves".to_string(),
+        ));
+    }
+    connection.transaction(|connection| {
+        for author_id in [from, into] {
+            if get_author(connection, author_id)?.is_none() {
+                return Err(BookyError::Validation(format!(
+                    "There is no author with id {}",
+                    author_id
+                )));
+            }
+        }
+        let links: Vec<BookAuthor> = book_authors::table
+            .filter(book_authors::author_id.eq(from))
+            .select(BookAuthor::as_select())
+            .load(connection)?;
+        // A book that already has both keeps its link to `into`
+        for link in &links {
+            diesel::insert_into(book_authors::table)
+                .values(BookAuthor {
+                    author_id: into,
+                    ..link.clone()
+                })
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+        }
+        diesel::delete(book_authors::table.filter(book_authors::author_id.eq(from)))
+            .execute(connection)?;
+        diesel::delete(authors::table.find(from)).execute(connection)?;
+        Ok(links.len())
+    })
+}
+
+/// Returns the ids of the books an author worked on, also those in the trash.
+pub fn get_author_book_ids(
+    connection: &mut SqliteConnection,
+    author_id: i32,
+) -> BookyResult<Vec<i32>> {
+    use crate::database::schema::book_authors;
+
+    let book_ids = book_authors::table
+        .filter(book_authors::author_id.eq(author_id))
+        .select(book_authors::book_id)
+        .distinct()
+        .load(connection)?;
+    Ok(book_ids)
+}
+
+fn find_or_create_author(connection: &mut SqliteConnection, name: &str) -> BookyResult<i32> {
+    use crate::database::schema::authors;
+
+    let key = author_key(name);
+    let found = authors::table
+        .filter(authors::key.eq(&key))
+        .select(authors::id)
+        .first(connection)
+        .optional()?;
+    if let Some(author_id) = found {
+        return Ok(author_id);
+    }
+
+    let author_id = diesel::insert_into(authors::table)
+        .values(NewAuthor {
+            name: name.to_string(),
+            key,
+        })
+        .returning(authors::id)
+        .get_result(connection)?;
+    Ok(author_id)
+}
+
+// Authors whose last book was deleted or changed
+fn delete_orphan_authors(connection: &mut SqliteConnection) -> BookyResult<()> {
+    use crate::database::schema::{authors, book_authors};
+
+    let linked = book_authors::table.select(book_authors::author_id);
+    diesel::delete(authors::table.filter(diesel::dsl::not(authors::id.eq_any(linked))))
+        .execute(connection)?;
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct AuthorBackfill {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    book_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    author: String,
+}
+
+// The create_authors migration keeps the old author strings in author_backfill
+fn backfill_authors(connection: &mut SqliteConnection) -> BookyResult<()> {
+    if !table_exists(connection, "author_backfill")? {
+        return Ok(());
+    }
+
+    connection.transaction(|connection| {
+        let rows: Vec<AuthorBackfill> =
+            diesel::sql_query("SELECT book_id, author FROM author_backfill").load(connection)?;
+        for row in &rows {
+            set_book_authors(connection, row.book_id, &row.author)?;
+        }
+        diesel::sql_query("DROP TABLE author_backfill").execute(connection)?;
+        Ok(())
+    })
+}
+
+/// Replaces the tags of `book` with the ones in `tags_text`.
+///
+/// Tags are created when they don't exist yet, see [`split_tags`] for the format.
+pub fn set_book_tags(
+    connection: &mut SqliteConnection,
+    book: i32,
+    tags_text: &str,
+) -> BookyResult<()> {
+    use crate::database::schema::book_tags;
+
+    diesel::delete(book_tags::table.filter(book_tags::book_id.eq(book))).execute(connection)?;
+    for name in split_tags(tags_text) {
+        let tag_id = find_or_create_tag(connection, &name)?;
+        diesel::insert_into(book_tags::table)
+            .values(BookTag {
+                book_id: book,
+                tag_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Returns every tag with how many books have it.
+pub fn get_tags(connection: &mut SqliteConnection) -> BookyResult<Vec<TagSummary>> {
+    let results = diesel::sql_query(
+        "SELECT t.id, t.name, count(b.id) AS books
+        FROM tags t
+        LEFT JOIN book_tags bt ON bt.tag_id = t.id
+        LEFT JOIN books b ON b.id = bt.book_id AND b.deleted_at IS NULL
+        GROUP BY t.id
+        ORDER BY t.name",
+    )
+    .load(connection)?;
+    Ok(results)
+}
+
+pub fn get_shelves(connection: &mut SqliteConnection) -> BookyResult<Vec<Shelf>> {
+    use crate::database::schema::shelves;
+
+    Ok(shelves::table
+        .order(shelves::name)
+        .select(Shelf::as_select())
+        .load(connection)?)
+}
+
+/// Finds a shelf by its name, case doesn't matter.
+pub fn get_shelf(connection: &mut SqliteConnection, name: &str) -> BookyResult<Option<Shelf>> {
+    use crate::database::schema::shelves;
+
+    let found = shelves::table
+        .filter(shelves::name.eq(name.trim()))
+        .select(Shelf::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(found)
+}
+
+/// Returns the tag filter of a shelf, its books are [`get_tagged_books`] with it.
+pub fn get_shelf_filter(
+    connection: &mut SqliteConnection,
+    shelf: &Shelf,
+) -> BookyResult<TagFilter> {
+    use crate::database::schema::{shelf_tags, tags};
+
+    let names = shelf_tags::table
+        .inner_join(tags::table)
+        .filter(shelf_tags::shelf_id.eq(shelf.id))
+        .order(tags::name)
+        .select(tags::name)
+        .load(connection)?;
+    Ok(TagFilter {
+        tags: names,
+        mode: if shelf.match_all {
+            TagMatch::All
+        } else {
+            TagMatch::Any
+        },
+    })
+}
+
+/// Saves `tag_filter` as a shelf called `name`.
+pub fn create_shelf(
+    connection: &mut SqliteConnection,
+    name: &str,
+    tag_filter: &TagFilter,
+) -> BookyResult<Shelf> {
+    use crate::database::schema::{shelf_tags, shelves};
+
+    connection.transaction(|connection| {
+        let shelf = diesel::insert_into(shelves::table)
+            .values(NewShelf {
+                name: name.trim().to_string(),
+                match_all: tag_filter.mode == TagMatch::All,
+            })
+            .returning(Shelf::as_returning())
+            .get_result(connection)?;
+        for name in &tag_filter.tags {
+            let tag_id = find_or_create_tag(connection, name)?;
+            diesel::insert_into(shelf_tags::table)
+                .values(ShelfTag {
+                    shelf_id: shelf.id,
+                    tag_id,
+                })
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+        }
+        Ok(shelf)
+    })
+}
+
+/// Deletes a shelf, its books and tags are kept.
+pub fn delete_shelf(connection: &mut SqliteConnection, shelf_id: i32) -> BookyResult<()> {
+    use crate::database::schema::shelves;
+
+    diesel::delete(shelves::table.find(shelf_id)).execute(connection)?;
+    delete_orphan_tags(connection)?;
+    Ok(())
+}
+
+fn find_or_create_tag(connection: &mut SqliteConnection, name: &str) -> BookyResult<i32> {
+    use crate::database::schema::tags;
+
+    let found = tags::table
+        .filter(tags::name.eq(name))
+        .select(tags::id)
+        .first(connection)
+        .optional()?;
+    if let Some(tag_id) = found {
+        return Ok(tag_id);
+    }
+
+    let tag_id = diesel::insert_into(tags::table)
+        .values(tags::name.eq(name))
+        .returning(tags::id)
+        .get_result(connection)?;
+    Ok(tag_id)
+}
+
+// Tags that are on no book and no shelf anymore
+fn delete_orphan_tags(connection: &mut SqliteConnection) -> BookyResult<()> {
+    use crate::database::schema::{book_tags, shelf_tags, tags};
+
+    let on_books = book_tags::table.select(book_tags::tag_id);
+    let on_shelves = shelf_tags::table.select(shelf_tags::tag_id);
+    diesel::delete(
+        tags::table
+            .filter(diesel::dsl::not(tags::id.eq_any(on_books)))
+            .filter(diesel::dsl::not(tags::id.eq_any(on_shelves))),
+    )
+    .execute(connection)?;
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct TagBackfill {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    book_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    tags: String,
+}
+
+// The create_tags migration keeps the old genres in tag_backfill
+fn backfill_tags(connection: &mut SqliteConnection) -> BookyResult<()> {
+    if !table_exists(connection, "tag_backfill")? {
+        return Ok(());
+    }
+
+    connection.transaction(|connection| {
+        let rows: Vec<TagBackfill> =
+            diesel::sql_query("SELECT book_id, tags FROM tag_backfill").load(connection)?;
+        for row in &rows {
+            set_book_tags(connection, row.book_id, &row.tags)?;
+        }
+        diesel::sql_query("DROP TABLE tag_backfill").execute(connection)?;
+        Ok(())
+    })
+}
+
+/// Puts `book` in the series called `name` as volume `index`.
+///
+/// The series is created when it doesn't exist yet, without a name the book
+/// is in no series.
+pub fn set_book_series(
+    connection: &mut SqliteConnection,
+    book: i32,
+    name: Option<&str>,
+    index: Option<f64>,
+) -> BookyResult<()> {
+    use crate::database::schema::books;
+
+    let series_id = match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => Some(find_or_create_series(connection, name)?),
+        None => None,
+    };
+    diesel::update(books::table.find(book))
+        .set((
+            books::series_id.eq(series_id),
+            books::series_index.eq(series_id.and(index)),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Returns every series with how many of its volumes are in the library and were read.
+pub fn get_series_list(connection: &mut SqliteConnection) -> BookyResult<Vec<SeriesSummary>> {
+    let results = diesel::sql_query(
+        "SELECT s.id, s.name, count(o.id) AS volumes,
+            count(CASE WHEN o.status = 'finished' OR o.times_read > 0 THEN 1 END) AS read
+        FROM series s
+        JOIN books b ON b.series_id = s.id
+        JOIN book_overview o ON o.id = b.id
+        GROUP BY s.id
+        ORDER BY s.name",
+    )
+    .load(connection)?;
+    Ok(results)
+}
+
+pub fn get_series(
+    connection: &mut SqliteConnection,
+    series_id: i32,
+) -> BookyResult<Option<Series>> {
+    use crate::database::schema::series;
+
+    let found = series::table
+        .find(series_id)
+        .select(Series::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(found)
+}
+
+/// Finds a series by its name, case doesn't matter.
+pub fn get_series_by_name(
+    connection: &mut SqliteConnection,
+    name: &str,
+) -> BookyResult<Option<Series>> {
+    use crate::database::schema::series;
+
+    let found = series::table
+        .filter(series::name.eq(name.trim()))
+        .select(Series::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(found)
+}
+
+/// Returns the volumes of a series in order, volumes without a number come last.
+pub fn get_series_books(
+    connection: &mut SqliteConnection,
+    series_id: i32,
+) -> BookyResult<Vec<Book>> {
+    use crate::database::schema::books;
+
+    let in_series = books::table
+        .filter(books::series_id.eq(series_id))
+        .select(books::id);
+    let results = book_overview::table
+        .filter(book_overview::id.eq_any(in_series))
+        .order((
+            book_overview::series_index.is_null(),
+            book_overview::series_index,
+            book_overview::id,
+        ))
         .select(Book::as_select())
-        .filter(title.like(title_pattern))
-        .filter(author.like(author_pattern))
-        .filter(genre.like(genre_pattern))
-        .filter(status.like(status_pattern))
-        .filter(rating.ge(book_info.rating))
-        .filter(start_date.ge(book_info.start_date))
-        .filter(end_date.le(book_info.end_date))
-        .load(connection)
-        .expect("Failed to find books");
-    results
+        .load(connection)?;
+    Ok(results)
+}
+
+/// Returns the first volume of a series that wasn't read yet.
+pub fn get_next_unread(
+    connection: &mut SqliteConnection,
+    series_id: i32,
+) -> BookyResult<Option<Book>> {
+    let books = get_series_books(connection, series_id)?;
+    Ok(books.into_iter().find(|book| !book.is_read()))
+}
+
+fn find_or_create_series(connection: &mut SqliteConnection, name: &str) -> BookyResult<i32> {
+    use crate::database::schema::series;
+
+    let found = series::table
+        .filter(series::name.eq(name))
+        .select(series::id)
+        .first(connection)
+        .optional()?;
+    if let Some(series_id) = found {
+        return Ok(series_id);
+    }
+
+    let series_id = diesel::insert_into(series::table)
+        .values(series::name.eq(name))
+        .returning(series::id)
+        .get_result(connection)?;
+    Ok(series_id)
+}
+
+// Series whose last volume was deleted or moved to another series
+fn delete_orphan_series(connection: &mut SqliteConnection) -> BookyResult<()> {
+    use crate::database::schema::{books, series};
+
+    let used = books::table
+        .filter(books::series_id.is_not_null())
+        .select(books::series_id.assume_not_null());
+    diesel::delete(series::table.filter(diesel::dsl::not(series::id.eq_any(used))))
+        .execute(connection)?;
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct SeriesBackfill {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    book_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    title: String,
+}
+
+// The create_series migration keeps the old titles in series_backfill
+fn backfill_series(connection: &mut SqliteConnection) -> BookyResult<()> {
+    use crate::database::schema::books;
+
+    if !table_exists(connection, "series_backfill")? {
+        return Ok(());
+    }
+
+    connection.transaction(|connection| {
+        let rows: Vec<SeriesBackfill> =
+            diesel::sql_query("SELECT book_id, title FROM series_backfill").load(connection)?;
+        for row in &rows {
+            let (title, Some((name, index))) = split_series_title(&row.title) else {
+                continue;
+            };
+            diesel::update(books::table.find(row.book_id))
+                .set(books::title.eq(title))
+                .execute(connection)?;
+            set_book_series(connection, row.book_id, Some(&name), Some(index))?;
+        }
+        diesel::sql_query("DROP TABLE series_backfill").execute(connection)?;
+        Ok(())
+    })
+}
+
+fn table_exists(connection: &mut SqliteConnection, name: &str) -> BookyResult<bool> {
+    use diesel::sql_types::{Bool, Text};
+
+    let exists = diesel::select(
+        diesel::dsl::sql::<Bool>(
+            "EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ",
+        )
+        .bind::<Text, _>(name)
+        .sql(")"),
+    )
+    .get_result(connection)?;
+    Ok(exists)
+}
+
+fn insert_record(connection: &mut SqliteConnection, record: &BookRecord) -> BookyResult<i32> {
+    use crate::database::schema::books;
+
+    let book_id = diesel::insert_into(books::table)
+        .values(record)
+        .returning(books::id)
+        .get_result(connection)?;
+    Ok(book_id)
+}
+
+// The latest session is the one book_overview shows
+fn latest_session(
+    connection: &mut SqliteConnection,
+    book_id: i32,
+) -> BookyResult<Option<ReadingSession>> {
+    Ok(get_sessions(connection, book_id)?.pop())
+}
+
+// A book that was just written is always there
+fn find_book(connection: &mut SqliteConnection, book_id: i32) -> BookyResult<Book> {
+    get_book(connection, book_id)?.ok_or_else(|| diesel::result::Error::NotFound.into())
+}
+
+/// Returns the quotes of a book in the order they were added.
+pub fn get_quotes(connection: &mut SqliteConnection, book: i32) -> BookyResult<Vec<Quote>> {
+    use crate::database::schema::quotes;
+
+    let results = quotes::table
+        .filter(quotes::book_id.eq(book))
+        .order(quotes::id)
+        .select(Quote::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+pub fn get_quote(connection: &mut SqliteConnection, quote_id: i32) -> BookyResult<Option<Quote>> {
+    use crate::database::schema::quotes;
+
+    let quote = quotes::table
+        .find(quote_id)
+        .select(Quote::as_select())
+        .first(connection)
+        .optional()?;
+    Ok(quote)
+}
+
+/// Returns the quotes of all books that aren't in the trash, used by exports.
+pub fn get_all_quotes(connection: &mut SqliteConnection) -> BookyResult<Vec<Quote>> {
+    use crate::database::schema::{books, quotes};
+
+    let results = quotes::table
+        .inner_join(books::table)
+        .filter(books::deleted_at.is_null())
+        .order(quotes::id)
+        .select(Quote::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+pub fn create_quote(connection: &mut SqliteConnection, new_quote: &NewQuote) -> BookyResult<Quote> {
+    use crate::database::schema::quotes;
+
+    let quote = diesel::insert_into(quotes::table)
+        .values(new_quote)
+        .returning(Quote::as_returning())
+        .get_result(connection)?;
+    Ok(quote)
+}
+
+pub fn update_quote(
+    connection: &mut SqliteConnection,
+    quote_id: i32,
+    update_quote: &NewQuote,
+) -> BookyResult<Quote> {
+    use crate::database::schema::quotes;
+
+    let quote = diesel::update(quotes::table.find(quote_id))
+        .set(update_quote)
+        .returning(Quote::as_returning())
+        .get_result(connection)?;
+    Ok(quote)
+}
+
+/// Returns the days a book was read on, oldest first.
+pub fn get_reading_log(
+    connection: &mut SqliteConnection,
+    book: i32,
+) -> BookyResult<Vec<ReadingDay>> {
+    use crate::database::schema::reading_log;
+
+    let results = reading_log::table
+        .filter(reading_log::book_id.eq(book))
+        .order(reading_log::day)
+        .select(ReadingDay::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+/// Returns the reading log of all books that aren't in the trash, used by exports.
+pub fn get_full_reading_log(connection: &mut SqliteConnection) -> BookyResult<Vec<ReadingDay>> {
+    use crate::database::schema::{books, reading_log};
+
+    let results = reading_log::table
+        .inner_join(books::table)
+        .filter(books::deleted_at.is_null())
+        .order((reading_log::book_id, reading_log::day))
+        .select(ReadingDay::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+/// Saves the reading time of a day, replacing what was saved for that day before.
+pub fn log_reading(
+    connection: &mut SqliteConnection,
+    reading_day: &ReadingDay,
+) -> BookyResult<ReadingDay> {
+    use crate::database::schema::reading_log;
+
+    let reading_day = diesel::insert_into(reading_log::table)
+        .values(reading_day)
+        .on_conflict((reading_log::book_id, reading_log::day))
+        .do_update()
+        .set(reading_day)
+        .returning(ReadingDay::as_returning())
+        .get_result(connection)?;
+    Ok(reading_day)
 }
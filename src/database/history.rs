@@ -0,0 +1,556 @@
+//! Undo and redo, every change to the books and shelves is saved in the
+//! history table.
+
+use super::models::{
+    Book, HistoryEntry, NewBook, NewHistoryEntry, Quote, ReadingDay, ReadingSession, Shelf,
+    ShelfTag,
+};
+use super::schema::{
+    all_books, books, history, quotes, reading_log, reading_sessions, shelf_tags, shelves,
+};
+use crate::error::{BookyError, BookyResult};
+use crate::tags::TagMatch;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+/// The oldest changes are forgotten once there are more than this.
+pub const HISTORY_LENGTH: i32 = 100;
+
+/// Everything there is to know about a book, enough to put it back as it was.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub book: NewBook,
+    /// When the book was moved to the trash, `None` when it isn't in there.
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+    pub sessions: Vec<ReadingSession>,
+    pub quotes: Vec<Quote>,
+    pub reading_log: Vec<ReadingDay>,
+}
+
+/// A book before and after a change, `None` when it didn't exist.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BookChange {
+    pub book_id: i32,
+    pub before: Option<BookSnapshot>,
+    pub after: Option<BookSnapshot>,
+}
+
+/// A shelf with the names of its tags.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShelfSnapshot {
+    pub name: String,
+    pub match_all: bool,
+    pub tags: Vec<String>,
+}
+
+/// A shelf before and after a change, `None` when it didn't exist.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShelfChange {
+    pub shelf_id: i32,
+    pub before: Option<ShelfSnapshot>,
+    pub after: Option<ShelfSnapshot>,
+}
+
+/// Everything a change did, saved as JSON in [`HistoryEntry::changes`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Changes {
+    pub books: Vec<BookChange>,
+    #[serde(default)]
+    pub shelves: Vec<ShelfChange>,
+}
+
+/// A change that is still being made, see [`super::Library::batch`].
+pub(crate) struct PendingChange {
+    description: String,
+    /// The books as they were before the change, `None` for added books.
+    before: BTreeMap<i32, Option<BookSnapshot>>,
+    /// The same for the shelves.
+    shelves_before: BTreeMap<i32, Option<ShelfSnapshot>>,
+}
+
+impl PendingChange {
+    pub(crate) fn new(description: String) -> Self {
+        Self {
+            description,
+            before: BTreeMap::new(),
+            shelves_before: BTreeMap::new(),
+        }
+    }
+
+    /// Takes a snapshot of the books that are about to change.
+    ///
+    /// Only the first snapshot of a book counts, later ones would already
+    /// contain part of the change.
+    pub(crate) fn remember(
+        &mut self,
+        connection: &mut SqliteConnection,
+        book_ids: &[i32],
+    ) -> BookyResult<()> {
+        for &book_id in book_ids {
+            if let Entry::Vacant(entry) = self.before.entry(book_id) {
+                entry.insert(snapshot(connection, book_id)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Notes the books the change added, undoing it deletes them again.
+    pub(crate) fn added(&mut self, book_ids: &[i32]) {
+        for &book_id in book_ids {
+            self.before.entry(book_id).or_insert(None);
+        }
+    }
+
+    /// Like [`PendingChange::remember`], for a shelf.
+    pub(crate) fn remember_shelf(
+        &mut self,
+        connection: &mut SqliteConnection,
+        shelf_id: i32,
+    ) -> BookyResult<()> {
+        if let Entry::Vacant(entry) = self.shelves_before.entry(shelf_id) {
+            entry.insert(shelf_snapshot(connection, shelf_id)?);
+        }
+        Ok(())
+    }
+
+    /// Like [`PendingChange::added`], for a shelf.
+    pub(crate) fn added_shelf(&mut self, shelf_id: i32) {
+        self.shelves_before.entry(shelf_id).or_insert(None);
+    }
+}
+
+/// Returns the title of a book, also when it is in the trash.
+pub fn book_title(connection: &mut SqliteConnection, book_id: i32) -> BookyResult<Option<String>> {
+    let title = books::table
+        .find(book_id)
+        .select(books::title)
+        .first(connection)
+        .optional()?;
+    Ok(title)
+}
+
+/// Takes a snapshot of a book, `None` when there is no such book.
+pub fn snapshot(
+    connection: &mut SqliteConnection,
+    book_id: i32,
+) -> BookyResult<Option<BookSnapshot>> {
+    // all_books has the same columns as book_overview, in the same order
+    let book: Option<Book> = all_books::table
+        .find(book_id)
+        .first(connection)
+        .optional()?;
+    let Some(book) = book else {
+        return Ok(None);
+    };
+    let deleted_at = books::table
+        .find(book_id)
+        .select(books::deleted_at)
+        .first(connection)?;
+    Ok(Some(BookSnapshot {
+        book: NewBook::from(&book),
+        deleted_at,
+        sessions: super::get_sessions(connection, book_id)?,
+        quotes: super::get_quotes(connection, book_id)?,
+        reading_log: super::get_reading_log(connection, book_id)?,
+    }))
+}
+
+/// Takes a snapshot of a shelf, `None` when there is no such shelf.
+pub fn shelf_snapshot(
+    connection: &mut SqliteConnection,
+    shelf_id: i32,
+) -> BookyResult<Option<ShelfSnapshot>> {
+    let shelf: Option<Shelf> = shelves::table
+        .find(shelf_id)
+        .select(Shelf::as_select())
+        .first(connection)
+        .optional()?;
+    let Some(shelf) = shelf else {
+        return Ok(None);
+    };
+    let tag_filter = super::get_shelf_filter(connection, &shelf)?;
+    Ok(Some(ShelfSnapshot {
+        name: shelf.name,
+        match_all: tag_filter.mode == TagMatch::All,
+        tags: tag_filter.tags,
+    }))
+}
+
+/// Saves a finished change, unless it didn't change anything.
+///
+/// Saving a change forgets the changes that were undone, they can't be
+/// redone anymore.
+pub(crate) fn save(
+    connection: &mut SqliteConnection,
+    pending: PendingChange,
+) -> BookyResult<Option<HistoryEntry>> {
+    let PendingChange {
+        description,
+        before,
+        shelves_before,
+    } = pending;
+
+    let mut changes = Changes::default();
+    for (book_id, before) in before {
+        let after = snapshot(connection, book_id)?;
+        if before != after {
+            changes.books.push(BookChange {
+                book_id,
+                before,
+                after,
+            });
+        }
+    }
+    for (shelf_id, before) in shelves_before {
+        let after = shelf_snapshot(connection, shelf_id)?;
+        if before != after {
+            changes.shelves.push(ShelfChange {
+                shelf_id,
+                before,
+                after,
+            });
+        }
+    }
+    if changes.books.is_empty() && changes.shelves.is_empty() {
+        return Ok(None);
+    }
+
+    diesel::delete(history::table.filter(history::undone.eq(true))).execute(connection)?;
+    let entry = diesel::insert_into(history::table)
+        .values(NewHistoryEntry {
+            description,
+            changes: serde_json::to_string(&changes)
+                .map_err(|e| BookyError::History(format!("the change can't be saved: {}", e)))?,
+            created_at: chrono::Local::now().naive_local(),
+        })
+        .returning(HistoryEntry::as_returning())
+        .get_result(connection)?;
+    diesel::delete(history::table.filter(history::id.le(entry.id - HISTORY_LENGTH)))
+        .execute(connection)?;
+    Ok(Some(entry))
+}
+
+/// Returns the latest changes, newest first.
+pub fn get_history(
+    connection: &mut SqliteConnection,
+    limit: i64,
+) -> BookyResult<Vec<HistoryEntry>> {
+    let results = history::table
+        .order(history::id.desc())
+        .limit(limit)
+        .select(HistoryEntry::as_select())
+        .load(connection)?;
+    Ok(results)
+}
+
+/// Reverts the latest change that wasn't undone yet and returns it.
+///
+/// Every book and shelf the change touched is put back as it was before.
+pub fn undo(connection: &mut SqliteConnection) -> BookyResult<Option<HistoryEntry>> {
+    connection.transaction(|connection| {
+        let entry = history::table
+            .filter(history::undone.eq(false))
+            .order(history::id.desc())
+            .select(HistoryEntry::as_select())
+            .first(connection)
+            .optional()?;
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        let changes = read_changes(&entry)?;
+        for change in changes.books.iter().rev() {
+            restore_snapshot(connection, change.book_id, change.before.as_ref())?;
+        }
+        for change in changes.shelves.iter().rev() {
+            restore_shelf(connection, change.shelf_id, change.before.as_ref())?;
+        }
+        finish(connection, entry, true).map(Some)
+    })
+}
+
+/// Makes the change that was undone last again and returns it.
+pub fn redo(connection: &mut SqliteConnection) -> BookyResult<Option<HistoryEntry>> {
+    connection.transaction(|connection| {
+        let entry = history::table
+            .filter(history::undone.eq(true))
+            .order(history::id)
+            .select(HistoryEntry::as_select())
+            .first(connection)
+            .optional()?;
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        let changes = read_changes(&entry)?;
+        for change in &changes.books {
+            restore_snapshot(connection, change.book_id, change.after.as_ref())?;
+        }
+        for change in &changes.shelves {
+            restore_shelf(connection, change.shelf_id, change.after.as_ref())?;
+        }
+        finish(connection, entry, false).map(Some)
+    })
+}
+
+fn read_changes(entry: &HistoryEntry) -> BookyResult<Changes> {
+    serde_json::from_str(&entry.changes).map_err(|e| {
+        BookyError::History(format!(
+            "the entry \"{}\" is damaged: {}",
+            entry.description, e
+        ))
+    })
+}
+
+// Marks the entry and cleans up what the restored books and shelves no longer use
+fn finish(
+    connection: &mut SqliteConnection,
+    entry: HistoryEntry,
+    undone: bool,
+) -> BookyResult<HistoryEntry> {
+    super::delete_orphan_authors(connection)?;
+    super::delete_orphan_tags(connection)?;
+    super::delete_orphan_series(connection)?;
+    diesel::update(history::table.find(entry.id))
+        .set(history::undone.eq(undone))
+        .execute(connection)?;
+    Ok(HistoryEntry { undone, ..entry })
+}
+
+// Replaces a book with its snapshot, or deletes it when there is none
+fn restore_snapshot(
+    connection: &mut SqliteConnection,
+    book_id: i32,
+    snapshot: Option<&BookSnapshot>,
+) -> BookyResult<()> {
+    // Its sessions, quotes and log go with it
+    diesel::delete(books::table.find(book_id)).execute(connection)?;
+    let Some(snapshot) = snapshot else {
+        return Ok(());
+    };
+
+    super::insert_book(connection, Some(book_id), &snapshot.book)?;
+    diesel::update(books::table.find(book_id))
+        .set(books::deleted_at.eq(snapshot.deleted_at))
+        .execute(connection)?;
+    // They keep their ids, nothing else took them while the book was gone
+    diesel::insert_into(reading_sessions::table)
+        .values(&snapshot.sessions)
+        .execute(connection)?;
+    diesel::insert_into(quotes::table)
+        .values(&snapshot.quotes)
+        .execute(connection)?;
+    diesel::insert_into(reading_log::table)
+        .values(&snapshot.reading_log)
+        .execute(connection)?;
+    Ok(())
+}
+
+// Replaces a shelf with its snapshot, or deletes it when there is none
+fn restore_shelf(
+    connection: &mut SqliteConnection,
+    shelf_id: i32,
+    snapshot: Option<&ShelfSnapshot>,
+) -> BookyResult<()> {
+    diesel::delete(shelves::table.find(shelf_id)).execute(connection)?;
+    let Some(snapshot) = snapshot else {
+        return Ok(());
+    };
+
+    diesel::insert_into(shelves::table)
+        .values((
+            shelves::id.eq(shelf_id),
+            shelves::name.eq(&snapshot.name),
+            shelves::match_all.eq(snapshot.match_all),
+        ))
+        .execute(connection)?;
+    for name in &snapshot.tags {
+        let tag_id = super::find_or_create_tag(connection, name)?;
+        diesel::insert_into(shelf_tags::table)
+            .values(ShelfTag { shelf_id, tag_id })
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::memory_library as library;
+    use crate::database::models::{NewBook, NewQuote};
+    use crate::database::Library;
+    use crate::export;
+    use crate::import;
+    use crate::status::ReadingStatus;
+    use crate::tags::{TagFilter, TagMatch};
+
+    fn new_book(title: &str) -> NewBook {
+        NewBook {
+            title: title.to_string(),
+            author: "Frank Herbert".to_string(),
+            tags: "scifi".to_string(),
+            rating: 4,
+            status: ReadingStatus::Reading,
+            series: None,
+            series_index: None,
+            isbn: None,
+            publisher: None,
+            published_year: None,
+            pages: None,
+            language: None,
+            format: None,
+            start_date: None,
+            end_date: None,
+            start_precision: Default::default(),
+            end_precision: Default::default(),
+        }
+    }
+
+    // Everything an export holds but its time, and the trash
+    fn state(library: &mut Library) -> String {
+        let mut json = Vec::new();
+        export::write_json(
+            library.books().unwrap(),
+            library.all_sessions().unwrap(),
+            library.all_quotes().unwrap(),
+            library.full_reading_log().unwrap(),
+            &mut json,
+        )
+        .unwrap();
+        let mut json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        json["exported_at"].take();
+        let trash: Vec<i32> = library
+            .trash()
+            .unwrap()
+            .iter()
+            .map(|book| book.id)
+            .collect();
+        format!("{}\n{:?}", json, trash)
+    }
+
+    #[test]
+    fn undo_and_redo_walk_back_and_forth_through_every_change() {
+        let mut library = library();
+        let mut states = vec![state(&mut library)];
+
+        let dune = library.create_book(&new_book("Dune")).unwrap();
+        states.push(state(&mut library));
+        library
+            .create_quote(&NewQuote {
+                book_id: dune.id,
+                text: "Fear is the mind-killer.".to_string(),
+                location: None,
+                added_at: None,
+            })
+            .unwrap();
+        states.push(state(&mut library));
+        let mut edited = new_book("Dune Messiah");
+        edited.status = ReadingStatus::Finished;
+        edited.rating = 5;
+        library.update_book(dune.id, &edited).unwrap();
+        states.push(state(&mut library));
+        let export = r#"{
+            "version": 1,
+            "books": [{
+                "id": 7, "title": "Children of Dune", "author": "Frank Herbert", "tags": "",
+                "rating": 3, "status": "finished",
+                "start_date": "2024-01-01", "end_date": "2024-02-01"
+            }]
+        }"#;
+        import::json::import(&mut library, export.as_bytes()).unwrap();
+        states.push(state(&mut library));
+        library.delete_book(dune.id).unwrap();
+        states.push(state(&mut library));
+
+        for expected in states.iter().rev().skip(1) {
+            library.undo().unwrap().unwrap();
+            assert_eq!(&state(&mut library), expected);
+        }
+        assert!(library.undo().unwrap().is_none());
+
+        for expected in states.iter().skip(1) {
+            library.redo().unwrap().unwrap();
+            assert_eq!(&state(&mut library), expected);
+        }
+        assert!(library.redo().unwrap().is_none());
+    }
+
+    #[test]
+    fn undoing_an_import_removes_its_books() {
+        let mut library = library();
+        // The sessions are added after their books, in the same batch
+        let export = r#"{
+            "version": 1,
+            "books": [{
+                "id": 1, "title": "Dune", "author": "Frank Herbert", "tags": "",
+                "rating": 4, "status": "finished",
+                "start_date": "2024-01-01", "end_date": "2024-02-01"
+            }]
+        }"#;
+        import::json::import(&mut library, export.as_bytes()).unwrap();
+        assert_eq!(library.sessions(1).unwrap().len(), 1);
+
+        library.undo().unwrap().unwrap();
+        assert!(library.books().unwrap().is_empty());
+        assert!(library.trash().unwrap().is_empty());
+        assert!(library.authors().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undoing_a_clippings_import_removes_its_books() {
+        let mut library = library();
+        let clippings = "Dune (Frank Herbert)\n\
+            - Your Highlight on page 12 | Location 150-152 | Added on Sunday, 5 March 2023 14:02:11\n\
+            \n\
+            Fear is the mind-killer.\n\
+            ==========\n";
+        import::kindle::import(&mut library, clippings.as_bytes(), false).unwrap();
+        assert_eq!(library.books().unwrap().len(), 1);
+
+        library.undo().unwrap().unwrap();
+        assert!(library.books().unwrap().is_empty());
+        assert!(library.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_brings_back_deleted_shelves_and_expired_books() {
+        let mut library = library();
+        let tag_filter = TagFilter {
+            tags: vec!["book club".to_string()],
+            mode: TagMatch::All,
+        };
+        let shelf = library.create_shelf("Club", &tag_filter).unwrap();
+        library.delete_shelf(shelf.id).unwrap();
+        library.undo().unwrap().unwrap();
+        let shelf = library.shelf("Club").unwrap().unwrap();
+        assert_eq!(library.shelf_filter(&shelf).unwrap(), tag_filter);
+
+        let book = library.create_book(&new_book("Dune")).unwrap();
+        library.delete_book(book.id).unwrap();
+        let tomorrow = chrono::Local::now().naive_local() + chrono::Duration::days(1);
+        assert_eq!(library.purge_deleted_before(tomorrow).unwrap(), 1);
+        let entry = library.undo().unwrap().unwrap();
+        assert_eq!(entry.description, "Purge the expired books in the trash");
+        assert_eq!(library.trash().unwrap()[0].id, book.id);
+    }
+
+    #[test]
+    fn undo_moves_a_quote_back_to_its_book() {
+        let mut library = library();
+        let dune = library.create_book(&new_book("Dune")).unwrap();
+        let messiah = library.create_book(&new_book("Dune Messiah")).unwrap();
+        let mut new_quote = NewQuote {
+            book_id: dune.id,
+            text: "Fear is the mind-killer.".to_string(),
+            location: None,
+            added_at: None,
+        };
+        let quote = library.create_quote(&new_quote).unwrap();
+        new_quote.book_id = messiah.id;
+        library.update_quote(quote.id, &new_quote).unwrap();
+
+        library.undo().unwrap().unwrap();
+        assert_eq!(library.quotes(dune.id).unwrap(), [quote]);
+        assert!(library.quotes(messiah.id).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+use super::history::{self, PendingChange};
+use super::models::{
+    Author, AuthorSummary, Book, BookQuery, DeletedBook, HistoryEntry, NewBook, NewQuote,
+    NewReadingSession, Quote, ReadingDay, ReadingSession, Series, SeriesSummary, Shelf, TagSummary,
+};
+use crate::error::BookyResult;
+use crate::tags::{TagFilter, TagMatch};
+use diesel::connection::{Connection, TransactionManager};
+use diesel::SqliteConnection;
+use std::path::Path;
+
+/// A book collection stored in a sqlite database.
+///
+/// This is the entry point for using booky as a library, it owns its
+/// connection and knows nothing about the tui. Every method is a thin
+/// wrapper around the functions in [`crate::database`].
+///
+/// Every change to the books and shelves is saved in the history, so it can be undone
+/// with [`Library::undo`]. Going around the library, for example through
+/// [`Library::connection`], skips the history.
+pub struct Library {
+    connection: SqliteConnection,
+    /// The change that is being recorded, see [`Library::batch`].
+    pending: Option<PendingChange>,
+}
+
+impl Library {
+    /// Wraps an already established connection.
+    pub fn new(connection: SqliteConnection) -> Self {
+        Self {
+            connection,
+            pending: None,
+        }
+    }
+
+    /// Opens the database at `path` and applies any pending migrations.
+    pub fn open(path: &Path) -> BookyResult<Self> {
+        let mut library = Self::new(super::establish_connection(path)?);
+        library.run_migrations()?;
+        Ok(library)
+    }
+
+    /// Gives access to the connection for queries that aren't covered here.
+    pub fn connection(&mut self) -> &mut SqliteConnection {
+        &mut self.connection
+    }
+
+    /// Runs `f` in a transaction, everything it changed is rolled back when it fails.
+    pub fn transaction<T, F>(&mut self, f: F) -> BookyResult<T>
+    where
+        F: FnOnce(&mut Self) -> BookyResult<T>,
+    {
+        // Connection::transaction only hands out the connection, so manage it ourselves
+        type Manager = <SqliteConnection as Connection>::TransactionManager;
+
+        Manager::begin_transaction(&mut self.connection)?;
+        match f(self) {
+            Ok(value) => {
+                Manager::commit_transaction(&mut self.connection)?;
+                Ok(value)
+            }
+            Err(e) => {
+                Manager::rollback_transaction(&mut self.connection)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs `f` as a single change, undoing it reverts all of it at once.
+    ///
+    /// Like [`Library::transaction`] nothing is saved when `f` fails. A
+    /// batch inside another one becomes part of the outer change.
+    pub fn batch<T, F>(&mut self, description: impl Into<String>, f: F) -> BookyResult<T>
+    where
+        F: FnOnce(&mut Self) -> BookyResult<T>,
+    {
+        if self.pending.is_some() {
+            return f(self);
+        }
+        self.transaction(|library| {
+            library.pending = Some(PendingChange::new(description.into()));
+            let result = f(library);
+            // Taken out before checking the result, a failed batch must not linger
+            let pending = library.pending.take();
+            let value = result?;
+            if let Some(pending) = pending {
+                history::save(&mut library.connection, pending)?;
+            }
+            Ok(value)
+        })
+    }
+
+    // Remembers the books before the running batch changes them
+    fn touch(&mut self, book_ids: &[i32]) -> BookyResult<()> {
+        match &mut self.pending {
+            Some(pending) => pending.remember(&mut self.connection, book_ids),
+            None => Ok(()),
+        }
+    }
+
+    // Notes the books the running batch added
+    fn added(&mut self, book_ids: &[i32]) {
+        if let Some(pending) = &mut self.pending {
+            pending.added(book_ids);
+        }
+    }
+
+    // Names a change to a single book, like `Update "Dune"`
+    fn describe(&mut self, action: &str, book_id: i32) -> BookyResult<String> {
+        Ok(match history::book_title(&mut self.connection, book_id)? {
+            Some(title) => format!("{} \"{}\"", action, title),
+            None => format!("{} book {}", action, book_id),
+        })
+    }
+
+    /// Reverts the latest change, `None` when there is nothing to undo.
+    pub fn undo(&mut self) -> BookyResult<Option<HistoryEntry>> {
+        history::undo(&mut self.connection)
+    }
+
+    /// Makes the last undone change again, `None` when there is nothing to redo.
+    pub fn redo(&mut self) -> BookyResult<Option<HistoryEntry>> {
+        history::redo(&mut self.connection)
+    }
+
+    pub fn history(&mut self, limit: i64) -> BookyResult<Vec<HistoryEntry>> {
+        history::get_history(&mut self.connection, limit)
+    }
+
+    pub fn run_migrations(&mut self) -> BookyResult<Vec<String>> {
+        super::run_migrations(&mut self.connection)
+    }
+
+    pub fn pending_migrations(&mut self) -> BookyResult<Vec<String>> {
+        super::pending_migrations(&mut self.connection)
+    }
+
+    pub fn schema_version(&mut self) -> BookyResult<Option<String>> {
+        super::schema_version(&mut self.connection)
+    }
+
+    pub fn books(&mut self) -> BookyResult<Vec<Book>> {
+        super::get_books(&mut self.connection)
+    }
+
+    pub fn book(&mut self, book_id: i32) -> BookyResult<Option<Book>> {
+        super::get_book(&mut self.connection, book_id)
+    }
+
+    pub fn search(&mut self, book_info: &BookQuery, tag_match: TagMatch) -> BookyResult<Vec<Book>> {
+        super::search_book(&mut self.connection, book_info, tag_match)
+    }
+
+    pub fn tagged_books(&mut self, tag_filter: &TagFilter) -> BookyResult<Vec<Book>> {
+        super::get_tagged_books(&mut self.connection, tag_filter)
+    }
+
+    pub fn create_book(&mut self, new_book: &NewBook) -> BookyResult<Book> {
+        self.batch(format!("Add \"{}\"", new_book.title), |library| {
+            let book = super::create_book(&mut library.connection, new_book)?;
+            library.added(&[book.id]);
+            Ok(book)
+        })
+    }
+
+    pub fn insert_book(&mut self, book_id: Option<i32>, new_book: &NewBook) -> BookyResult<Book> {
+        self.batch(format!("Add \"{}\"", new_book.title), |library| {
+            let book = super::insert_book(&mut library.connection, book_id, new_book)?;
+            library.added(&[book.id]);
+            Ok(book)
+        })
+    }
+
+    pub fn create_books(&mut self, new_books: &[NewBook]) -> BookyResult<Vec<Book>> {
+        self.batch(format!("Add {} books", new_books.len()), |library| {
+            let books = super::create_books(&mut library.connection, new_books)?;
+            let book_ids: Vec<i32> = books.iter().map(|book| book.id).collect();
+            library.added(&book_ids);
+            Ok(books)
+        })
+    }
+
+    pub fn update_book(&mut self, book_id: i32, book: &NewBook) -> BookyResult<Book> {
+        let description = self.describe("Update", book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[book_id])?;
+            super::update_book(&mut library.connection, book_id, book)
+        })
+    }
+
+    pub fn delete_book(&mut self, book_id: i32) -> BookyResult<()> {
+        let description = self.describe("Delete", book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[book_id])?;
+            super::delete_book(&mut library.connection, book_id)
+        })
+    }
+
+    pub fn delete_books(&mut self, book_ids: &[i32]) -> BookyResult<usize> {
+        self.batch(format!("Delete {} books", book_ids.len()), |library| {
+            library.touch(book_ids)?;
+            super::delete_books(&mut library.connection, book_ids)
+        })
+    }
+
+    pub fn trash(&mut self) -> BookyResult<Vec<DeletedBook>> {
+        super::get_trash(&mut self.connection)
+    }
+
+    pub fn restore_book(&mut self, book_id: i32) -> BookyResult<Book> {
+        let description = self.describe("Restore", book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[book_id])?;
+            super::restore_book(&mut library.connection, book_id)
+        })
+    }
+
+    pub fn purge_books(&mut self, book_ids: &[i32]) -> BookyResult<usize> {
+        let description = match book_ids {
+            [book_id] => self.describe("Purge", *book_id)?,
+            _ => format!("Purge {} books", book_ids.len()),
+        };
+        self.batch(description, |library| {
+            library.touch(book_ids)?;
+            super::purge_books(&mut library.connection, book_ids)
+        })
+    }
+
+    pub fn purge_deleted_before(&mut self, cutoff: chrono::NaiveDateTime) -> BookyResult<usize> {
+        self.batch("Purge the expired books in the trash", |library| {
+            let book_ids = super::get_deleted_before(&mut library.connection, cutoff)?;
+            library.touch(&book_ids)?;
+            super::purge_books(&mut library.connection, &book_ids)
+        })
+    }
+
+    pub fn authors(&mut self) -> BookyResult<Vec<AuthorSummary>> {
+        super::get_authors(&mut self.connection)
+    }
+
+    pub fn author(&mut self, author_id: i32) -> BookyResult<Option<Author>> {
+        super::get_author(&mut self.connection, author_id)
+    }
+
+    pub fn merge_authors(&mut self, from: i32, into: i32) -> BookyResult<usize> {
+        let name = |author: Option<Author>| author.map(|author| author.name).unwrap_or_default();
+        let description = format!(
+            "Merge \"{}\" into \"{}\"",
+            name(self.author(from)?),
+            name(self.author(into)?)
+        );
+        self.batch(description, |library| {
+            let book_ids = super::get_author_book_ids(&mut library.connection, from)?;
+            library.touch(&book_ids)?;
+            super::merge_authors(&mut library.connection, from, into)
+        })
+    }
+
+    pub fn tags(&mut self) -> BookyResult<Vec<TagSummary>> {
+        super::get_tags(&mut self.connection)
+    }
+
+    pub fn shelves(&mut self) -> BookyResult<Vec<Shelf>> {
+        super::get_shelves(&mut self.connection)
+    }
+
+    pub fn shelf(&mut self, name: &str) -> BookyResult<Option<Shelf>> {
+        super::get_shelf(&mut self.connection, name)
+    }
+
+    pub fn shelf_filter(&mut self, shelf: &Shelf) -> BookyResult<TagFilter> {
+        super::get_shelf_filter(&mut self.connection, shelf)
+    }
+
+    pub fn create_shelf(&mut self, name: &str, tag_filter: &TagFilter) -> BookyResult<Shelf> {
+        self.batch(format!("Add the shelf \"{}\"", name.trim()), |library| {
+            let shelf = super::create_shelf(&mut library.connection, name, tag_filter)?;
+            if let Some(pending) = &mut library.pending {
+                pending.added_shelf(shelf.id);
+            }
+            Ok(shelf)
+        })
+    }
+
+    pub fn delete_shelf(&mut self, shelf_id: i32) -> BookyResult<()> {
+        let name = history::shelf_snapshot(&mut self.connection, shelf_id)?
+            .map(|shelf| shelf.name)
+            .unwrap_or_default();
+        self.batch(format!("Delete the shelf \"{}\"", name), |library| {
+            if let Some(pending) = &mut library.pending {
+                pending.remember_shelf(&mut library.connection, shelf_id)?;
+            }
+            super::delete_shelf(&mut library.connection, shelf_id)
+        })
+    }
+
+    pub fn series_list(&mut self) -> BookyResult<Vec<SeriesSummary>> {
+        super::get_series_list(&mut self.connection)
+    }
+
+    pub fn series(&mut self, series_id: i32) -> BookyResult<Option<Series>> {
+        super::get_series(&mut self.connection, series_id)
+    }
+
+    pub fn series_by_name(&mut self, name: &str) -> BookyResult<Option<Series>> {
+        super::get_series_by_name(&mut self.connection, name)
+    }
+
+    pub fn series_books(&mut self, series_id: i32) -> BookyResult<Vec<Book>> {
+        super::get_series_books(&mut self.connection, series_id)
+    }
+
+    pub fn next_unread(&mut self, series_id: i32) -> BookyResult<Option<Book>> {
+        super::get_next_unread(&mut self.connection, series_id)
+    }
+
+    pub fn sessions(&mut self, book_id: i32) -> BookyResult<Vec<ReadingSession>> {
+        super::get_sessions(&mut self.connection, book_id)
+    }
+
+    pub fn all_sessions(&mut self) -> BookyResult<Vec<ReadingSession>> {
+        super::get_all_sessions(&mut self.connection)
+    }
+
+    pub fn create_session(&mut self, session: &NewReadingSession) -> BookyResult<ReadingSession> {
+        let description = self.describe("Add a reading session to", session.book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[session.book_id])?;
+            super::create_session(&mut library.connection, session)
+        })
+    }
+
+    pub fn quotes(&mut self, book_id: i32) -> BookyResult<Vec<Quote>> {
+        super::get_quotes(&mut self.connection, book_id)
+    }
+
+    pub fn all_quotes(&mut self) -> BookyResult<Vec<Quote>> {
+        super::get_all_quotes(&mut self.connection)
+    }
+
+    pub fn create_quote(&mut self, new_quote: &NewQuote) -> BookyResult<Quote> {
+        let description = self.describe("Add a quote to", new_quote.book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[new_quote.book_id])?;
+            super::create_quote(&mut library.connection, new_quote)
+        })
+    }
+
+    pub fn update_quote(&mut self, quote_id: i32, quote: &NewQuote) -> BookyResult<Quote> {
+        let description = self.describe("Update a quote of", quote.book_id)?;
+        self.batch(description, |library| {
+            // The quote can move to another book, both books change
+            if let Some(old) = super::get_quote(&mut library.connection, quote_id)? {
+                library.touch(&[old.book_id])?;
+            }
+            library.touch(&[quote.book_id])?;
+            super::update_quote(&mut library.connection, quote_id, quote)
+        })
+    }
+
+    pub fn reading_log(&mut self, book_id: i32) -> BookyResult<Vec<ReadingDay>> {
+        super::get_reading_log(&mut self.connection, book_id)
+    }
+
+    pub fn full_reading_log(&mut self) -> BookyResult<Vec<ReadingDay>> {
+        super::get_full_reading_log(&mut self.connection)
+    }
+
+    pub fn log_reading(&mut self, reading_day: &ReadingDay) -> BookyResult<ReadingDay> {
+        let description = self.describe("Log reading", reading_day.book_id)?;
+        self.batch(description, |library| {
+            library.touch(&[reading_day.book_id])?;
+            super::log_reading(&mut library.connection, reading_day)
+        })
+    }
+}
@@ -1,28 +1,475 @@
-use super::schema::books;
+use super::schema::{
+    authors, book_authors, book_overview, book_tags, books, history, quotes, reading_log,
+    reading_sessions, series, shelf_tags, shelves, tags,
+};
+use crate::dates::{DatePrecision, PartialDate};
+use crate::status::ReadingStatus;
 use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Selectable, Clone, AsChangeset, Debug)]
-#[diesel(table_name = crate::database::schema::books)]
+/// A book with the dates of its latest reading session.
+#[derive(Queryable, Selectable, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[diesel(table_name = book_overview)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct Book {
     pub id: i32,
     pub title: String,
     pub author: String,
-    pub genre: String,
+    /// Comma separated, see [`crate::tags::split_tags`]. Older exports call it genre.
+    #[serde(alias = "genre")]
+    pub tags: String,
     pub rating: i32,
-    pub status: String,
+    pub status: ReadingStatus,
+    #[serde(default)]
+    pub series: Option<String>,
+    /// Number of the volume in its series.
+    #[serde(default)]
+    pub series_index: Option<f64>,
+    /// ISBN-13 without dashes, see [`crate::editions::parse_isbn`].
+    #[serde(default)]
+    pub isbn: Option<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+    #[serde(default)]
+    pub published_year: Option<i32>,
+    /// Page count of the edition.
+    #[serde(default)]
+    pub pages: Option<i32>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// One of [`crate::editions::FORMATS`].
+    #[serde(default)]
+    pub format: Option<String>,
     pub start_date: Option<chrono::NaiveDate>,
     pub end_date: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub start_precision: DatePrecision,
+    #[serde(default)]
+    pub end_precision: DatePrecision,
+    /// How many sessions were finished, the sessions are exported on their own.
+    #[serde(skip)]
+    pub times_read: i32,
 }
 
-#[derive(Insertable, AsChangeset, Debug)]
-#[diesel(table_name = books)]
+impl Book {
+    /// How often the book was read again after the first time.
+    pub fn rereads(&self) -> i32 {
+        (self.times_read - 1).max(0)
+    }
+
+    /// Whether the book was read at least once.
+    pub fn is_read(&self) -> bool {
+        self.times_read > 0 || self.status == ReadingStatus::Finished
+    }
+
+    /// The start date with as much of it as is known.
+    pub fn start(&self) -> Option<PartialDate> {
+        partial_date(self.start_date, self.start_precision)
+    }
+
+    /// The end date with as much of it as is known.
+    pub fn end(&self) -> Option<PartialDate> {
+        partial_date(self.end_date, self.end_precision)
+    }
+}
+
+/// A book in the trash, see [`crate::database::delete_books`].
+#[derive(QueryableByName, Clone, Debug)]
+pub struct DeletedBook {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub title: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub author: String,
+    #[diesel(sql_type = diesel::sql_types::Timestamp)]
+    pub deleted_at: chrono::NaiveDateTime,
+}
+
+/// A book as the user enters it, the dates are those of its latest session.
+///
+/// [`crate::database::create_book`] and [`crate::database::update_book`]
+/// split it into a row of the books table and a reading session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct NewBook {
     pub title: String,
     pub author: String,
-    pub genre: String,
+    /// Comma separated, like "fantasy, book club".
+    pub tags: String,
     pub rating: i32,
-    pub status: String,
+    pub status: ReadingStatus,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub isbn: Option<String>,
+    pub publisher: Option<String>,
+    pub published_year: Option<i32>,
+    pub pages: Option<i32>,
+    pub language: Option<String>,
+    pub format: Option<String>,
     pub start_date: Option<chrono::NaiveDate>,
     pub end_date: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub start_precision: DatePrecision,
+    #[serde(default)]
+    pub end_precision: DatePrecision,
+}
+
+impl NewBook {
+    /// The outcome of the latest session, going by the status and end date.
+    pub fn outcome(&self) -> Option<String> {
+        match self.status {
+            ReadingStatus::Abandoned => Some(ReadingSession::ABANDONED.to_string()),
+            _ if self.end_date.is_some() => Some(ReadingSession::FINISHED.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Book> for NewBook {
+    fn from(book: &Book) -> Self {
+        Self {
+            title: book.title.clone(),
+            author: book.author.clone(),
+            tags: book.tags.clone(),
+            rating: book.rating,
+            status: book.status,
+            series: book.series.clone(),
+            series_index: book.series_index,
+            isbn: book.isbn.clone(),
+            publisher: book.publisher.clone(),
+            published_year: book.published_year,
+            pages: book.pages,
+            language: book.language.clone(),
+            format: book.format.clone(),
+            start_date: book.start_date,
+            end_date: book.end_date,
+            start_precision: book.start_precision,
+            end_precision: book.end_precision,
+        }
+    }
+}
+
+fn partial_date(date: Option<chrono::NaiveDate>, precision: DatePrecision) -> Option<PartialDate> {
+    date.map(|date| PartialDate::new(date, precision))
+}
+
+/// What to search for, empty fields match every book.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookQuery {
+    pub title: String,
+    pub author: String,
+    /// Comma separated, see [`crate::tags::TagFilter`].
+    pub tags: String,
+    /// Books rated at least this.
+    pub rating: i32,
+    pub status: Option<ReadingStatus>,
+    /// Books started on or after this day.
+    pub start_date: Option<chrono::NaiveDate>,
+    /// Books finished on or before this day.
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+/// A row of the books table, a new book gets an id when `id` is `None`.
+///
+/// The authors are linked in `book_authors` and the tags in `book_tags`, see
+/// [`crate::database::set_book_authors`] and [`crate::database::set_book_tags`].
+/// The series is set by [`crate::database::set_book_series`].
+///
+/// Missing edition fields are saved as NULL, so updating a book clears them.
+#[derive(Insertable, AsChangeset, Clone, Debug)]
+#[diesel(table_name = books)]
+#[diesel(treat_none_as_null = true)]
+pub struct BookRecord {
+    pub id: Option<i32>,
+    pub title: String,
+    pub rating: i32,
+    pub status: ReadingStatus,
+    pub isbn: Option<String>,
+    pub publisher: Option<String>,
+    pub published_year: Option<i32>,
+    pub pages: Option<i32>,
+    pub language: Option<String>,
+    pub format: Option<String>,
+}
+
+impl From<&NewBook> for BookRecord {
+    fn from(book: &NewBook) -> Self {
+        Self {
+            id: None,
+            title: book.title.clone(),
+            rating: book.rating,
+            status: book.status,
+            isbn: book.isbn.clone(),
+            publisher: book.publisher.clone(),
+            published_year: book.published_year,
+            pages: book.pages,
+            language: book.language.clone(),
+            format: book.format.clone(),
+        }
+    }
+}
+
+/// One time a book was read, from start to finish or until it was put away.
+#[derive(
+    Queryable, Selectable, Identifiable, Insertable, Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[diesel(table_name = reading_sessions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReadingSession {
+    pub id: i32,
+    pub book_id: i32,
+    pub started: Option<chrono::NaiveDate>,
+    pub finished: Option<chrono::NaiveDate>,
+    /// [`ReadingSession::FINISHED`] or [`ReadingSession::ABANDONED`], `None` while reading.
+    pub outcome: Option<String>,
+    pub pages: Option<i32>,
+    pub minutes: Option<i32>,
+    #[serde(default)]
+    pub started_precision: DatePrecision,
+    #[serde(default)]
+    pub finished_precision: DatePrecision,
+}
+
+impl ReadingSession {
+    pub const FINISHED: &'static str = "finished";
+    pub const ABANDONED: &'static str = "abandoned";
+
+    pub fn start(&self) -> Option<PartialDate> {
+        partial_date(self.started, self.started_precision)
+    }
+
+    pub fn end(&self) -> Option<PartialDate> {
+        partial_date(self.finished, self.finished_precision)
+    }
+}
+
+#[derive(Insertable, AsChangeset, Clone, Debug)]
+#[diesel(table_name = reading_sessions)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewReadingSession {
+    pub book_id: i32,
+    pub started: Option<chrono::NaiveDate>,
+    pub finished: Option<chrono::NaiveDate>,
+    pub outcome: Option<String>,
+    pub pages: Option<i32>,
+    pub minutes: Option<i32>,
+    pub started_precision: DatePrecision,
+    pub finished_precision: DatePrecision,
+}
+
+impl NewReadingSession {
+    /// The session made from the dates of `book`.
+    ///
+    /// Books without any dates were never started, so they have no session.
+    pub fn from_dates(book_id: i32, book: &NewBook) -> Option<Self> {
+        if book.start_date.is_none() && book.end_date.is_none() {
+            return None;
+        }
+        Some(Self {
+            book_id,
+            started: book.start_date,
+            finished: book.end_date,
+            outcome: book.outcome(),
+            pages: None,
+            minutes: None,
+            started_precision: book.start_precision,
+            finished_precision: book.end_precision,
+        })
+    }
+}
+
+impl From<&ReadingSession> for NewReadingSession {
+    fn from(session: &ReadingSession) -> Self {
+        Self {
+            book_id: session.book_id,
+            started: session.started,
+            finished: session.finished,
+            outcome: session.outcome.clone(),
+            pages: session.pages,
+            minutes: session.minutes,
+            started_precision: session.started_precision,
+            finished_precision: session.finished_precision,
+        }
+    }
+}
+
+#[derive(
+    Queryable, Selectable, Identifiable, Insertable, Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[diesel(table_name = quotes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Quote {
+    pub id: i32,
+    pub book_id: i32,
+    pub text: String,
+    /// Where the quote is in the book, like "page 12 | Location 150-152".
+    pub location: Option<String>,
+    pub added_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable, AsChangeset, Clone, Debug)]
+#[diesel(table_name = quotes)]
+pub struct NewQuote {
+    pub book_id: i32,
+    pub text: String,
+    pub location: Option<String>,
+    pub added_at: Option<chrono::NaiveDateTime>,
+}
+
+/// How long a book was read on a single day.
+#[derive(
+    Queryable, Selectable, Insertable, AsChangeset, Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[diesel(table_name = reading_log)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReadingDay {
+    pub book_id: i32,
+    pub day: chrono::NaiveDate,
+    pub seconds: i32,
+    pub pages: i32,
+}
+
+/// A change to the books that can be undone, see [`crate::database::history`].
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug)]
+#[diesel(table_name = history)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HistoryEntry {
+    pub id: i32,
+    /// What was done, like `Update "Dune"`.
+    pub description: String,
+    /// JSON of [`crate::database::history::Changes`].
+    pub changes: String,
+    /// Undone changes can be redone until the next change is made.
+    pub undone: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = history)]
+pub struct NewHistoryEntry {
+    pub description: String,
+    pub changes: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// A person that wrote, translated or edited books.
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug, Serialize, Deserialize)]
+#[diesel(table_name = authors)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Author {
+    pub id: i32,
+    pub name: String,
+    /// See [`crate::authors::author_key`].
+    pub key: String,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = authors)]
+pub struct NewAuthor {
+    pub name: String,
+    pub key: String,
+}
+
+/// Links a book to one of its authors.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(table_name = book_authors)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookAuthor {
+    pub book_id: i32,
+    pub author_id: i32,
+    /// See [`crate::authors::AuthorRole::as_str`].
+    pub role: String,
+    /// Authors are shown in this order.
+    pub position: i32,
+}
+
+/// An author with how many of their books are in the library and were read.
+#[derive(QueryableByName, Clone, Debug)]
+pub struct AuthorSummary {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub books: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub read: i32,
+}
+
+/// A tag that can be put on any number of books.
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug, Serialize, Deserialize)]
+#[diesel(table_name = tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Tag {
+    pub id: i32,
+    /// Unique without regard to case.
+    pub name: String,
+}
+
+/// Links a book to one of its tags.
+#[derive(Queryable, Selectable, Insertable, Clone, Debug)]
+#[diesel(table_name = book_tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookTag {
+    pub book_id: i32,
+    pub tag_id: i32,
+}
+
+/// A tag with how many books have it.
+#[derive(QueryableByName, Clone, Debug)]
+pub struct TagSummary {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub books: i32,
+}
+
+/// A saved tag filter, the shelf holds every book that matches it.
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug)]
+#[diesel(table_name = shelves)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Shelf {
+    pub id: i32,
+    pub name: String,
+    /// Books need all tags of the shelf instead of any of them.
+    pub match_all: bool,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = shelves)]
+pub struct NewShelf {
+    pub name: String,
+    pub match_all: bool,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = shelf_tags)]
+pub struct ShelfTag {
+    pub shelf_id: i32,
+    pub tag_id: i32,
+}
+
+/// Books that are volumes of the same story.
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug, Serialize, Deserialize)]
+#[diesel(table_name = series)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Series {
+    pub id: i32,
+    /// Unique without regard to case.
+    pub name: String,
+}
+
+/// A series with how many of its volumes are in the library and were read.
+#[derive(QueryableByName, Clone, Debug)]
+pub struct SeriesSummary {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub volumes: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub read: i32,
 }
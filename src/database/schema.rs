@@ -1,14 +1,193 @@
 // @generated automatically by Diesel CLI.
 
+// Not generated, like book_overview but with the books in the trash
+diesel::table! {
+    all_books (id) {
+        id -> Integer,
+        title -> Text,
+        author -> Text,
+        tags -> Text,
+        rating -> Integer,
+        status -> Text,
+        series -> Nullable<Text>,
+        series_index -> Nullable<Double>,
+        isbn -> Nullable<Text>,
+        publisher -> Nullable<Text>,
+        published_year -> Nullable<Integer>,
+        pages -> Nullable<Integer>,
+        language -> Nullable<Text>,
+        format -> Nullable<Text>,
+        start_date -> Nullable<Date>,
+        end_date -> Nullable<Date>,
+        start_precision -> Text,
+        end_precision -> Text,
+        times_read -> Integer,
+    }
+}
+
+diesel::table! {
+    authors (id) {
+        id -> Integer,
+        name -> Text,
+        key -> Text,
+    }
+}
+
+diesel::table! {
+    book_authors (book_id, author_id, role) {
+        book_id -> Integer,
+        author_id -> Integer,
+        role -> Text,
+        position -> Integer,
+    }
+}
+
+diesel::table! {
+    book_tags (book_id, tag_id) {
+        book_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
 diesel::table! {
     books (id) {
+        id -> Integer,
+        title -> Text,
+        rating -> Integer,
+        status -> Text,
+        series_id -> Nullable<Integer>,
+        series_index -> Nullable<Double>,
+        isbn -> Nullable<Text>,
+        publisher -> Nullable<Text>,
+        published_year -> Nullable<Integer>,
+        pages -> Nullable<Integer>,
+        language -> Nullable<Text>,
+        format -> Nullable<Text>,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+// Not generated, book_overview is a view that diesel doesn't know about
+diesel::table! {
+    book_overview (id) {
         id -> Integer,
         title -> Text,
         author -> Text,
-        genre -> Text,
+        tags -> Text,
         rating -> Integer,
         status -> Text,
+        series -> Nullable<Text>,
+        series_index -> Nullable<Double>,
+        isbn -> Nullable<Text>,
+        publisher -> Nullable<Text>,
+        published_year -> Nullable<Integer>,
+        pages -> Nullable<Integer>,
+        language -> Nullable<Text>,
+        format -> Nullable<Text>,
         start_date -> Nullable<Date>,
         end_date -> Nullable<Date>,
+        start_precision -> Text,
+        end_precision -> Text,
+        times_read -> Integer,
+    }
+}
+
+diesel::table! {
+    history (id) {
+        id -> Integer,
+        description -> Text,
+        changes -> Text,
+        undone -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    quotes (id) {
+        id -> Integer,
+        book_id -> Integer,
+        text -> Text,
+        location -> Nullable<Text>,
+        added_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    reading_log (book_id, day) {
+        book_id -> Integer,
+        day -> Date,
+        seconds -> Integer,
+        pages -> Integer,
+    }
+}
+
+diesel::table! {
+    reading_sessions (id) {
+        id -> Integer,
+        book_id -> Integer,
+        started -> Nullable<Date>,
+        finished -> Nullable<Date>,
+        outcome -> Nullable<Text>,
+        pages -> Nullable<Integer>,
+        minutes -> Nullable<Integer>,
+        started_precision -> Text,
+        finished_precision -> Text,
+    }
+}
+
+diesel::table! {
+    series (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    shelf_tags (shelf_id, tag_id) {
+        shelf_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    shelves (id) {
+        id -> Integer,
+        name -> Text,
+        match_all -> Bool,
     }
 }
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+diesel::joinable!(book_authors -> authors (author_id));
+diesel::joinable!(book_authors -> books (book_id));
+diesel::joinable!(book_tags -> books (book_id));
+diesel::joinable!(book_tags -> tags (tag_id));
+diesel::joinable!(quotes -> books (book_id));
+diesel::joinable!(reading_log -> books (book_id));
+diesel::joinable!(books -> series (series_id));
+diesel::joinable!(reading_sessions -> books (book_id));
+diesel::joinable!(shelf_tags -> shelves (shelf_id));
+diesel::joinable!(shelf_tags -> tags (tag_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    all_books,
+    authors,
+    book_authors,
+    book_overview,
+    book_tags,
+    books,
+    history,
+    quotes,
+    reading_log,
+    reading_sessions,
+    series,
+    shelf_tags,
+    shelves,
+    tags,
+);
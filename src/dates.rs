@@ -0,0 +1,253 @@
+use crate::error::{BookyError, BookyResult};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How much of a date is known.
+///
+/// A date that is only known to the year is stored as January 1st and one
+/// known to the month as the first of that month, the precision keeps them
+/// apart from dates that really are the first.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "kebab-case")]
+pub enum DatePrecision {
+    #[default]
+    Day,
+    Month,
+    Year,
+}
+
+impl DatePrecision {
+    pub const ALL: [DatePrecision; 3] = [
+        DatePrecision::Day,
+        DatePrecision::Month,
+        DatePrecision::Year,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DatePrecision::Day => "day",
+            DatePrecision::Month => "month",
+            DatePrecision::Year => "year",
+        }
+    }
+}
+
+impl FromStr for DatePrecision {
+    type Err = BookyError;
+
+    fn from_str(s: &str) -> BookyResult<Self> {
+        DatePrecision::ALL
+            .into_iter()
+            .find(|precision| precision.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                BookyError::Validation(format!(
+                    "Date precision must be day, month or year, got \"{}\"",
+                    s.trim()
+                ))
+            })
+    }
+}
+
+impl ToSql<Text, Sqlite> for DatePrecision {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.as_str());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for DatePrecision {
+    fn from_sql(bytes: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        DatePrecision::ALL
+            .into_iter()
+            .find(|precision| precision.as_str() == value)
+            .ok_or_else(|| format!("Unknown date precision \"{}\" in the database", value).into())
+    }
+}
+
+/// The order of the day and month in dates like `12/04/2023`.
+///
+/// Set with the `date_order` key of the config file, dates written as
+/// `2023-04-12` always start with the year.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateOrder {
+    /// `12/04/2023` is the 12th of April.
+    #[default]
+    DayMonth,
+    /// `12/04/2023` is the 4th of December.
+    MonthDay,
+}
+
+/// A date that may only be known to the month or year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDate {
+    /// The first day the date can be.
+    pub date: NaiveDate,
+    pub precision: DatePrecision,
+}
+
+impl PartialDate {
+    pub fn new(date: NaiveDate, precision: DatePrecision) -> Self {
+        Self { date, precision }
+    }
+
+    /// The last day the date can be, the end of its month or year.
+    pub fn last_day(self) -> NaiveDate {
+        let next = match self.precision {
+            DatePrecision::Day => return self.date,
+            DatePrecision::Month => self.date.checked_add_months(Months::new(1)),
+            DatePrecision::Year => self.date.checked_add_months(Months::new(12)),
+        };
+        next.and_then(|next| next.pred_opt()).unwrap_or(self.date)
+    }
+
+    /// Reads a typed date, `None` when it isn't one.
+    ///
+    /// Understands `2023`, `2023-04`, `2023-04-12`, `12/04/2023` in the
+    /// given `order` and the words `today`, `yesterday` and `last week`,
+    /// which count back from `today`.
+    pub fn parse(text: &str, order: DateOrder, today: NaiveDate) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        let relative = match text.as_str() {
+            "today" => Some(today),
+            "yesterday" => today.pred_opt(),
+            "last week" => today.checked_sub_signed(Duration::weeks(1)),
+            _ => None,
+        };
+        if let Some(date) = relative {
+            return Some(Self::new(date, DatePrecision::Day));
+        }
+
+        let numbers: Vec<&str> = text.split(['-', '/', '.']).collect();
+        if numbers.iter().any(|n| n.is_empty() || !n.is_ascii()) {
+            return None;
+        }
+        // The year is the part with four digits, the others are in `order`
+        let (year, month, day) = match numbers.as_slice() {
+            [year] if year.len() == 4 => (*year, None, None),
+            [year, month] if year.len() == 4 => (*year, Some(*month), None),
+            [year, month, day] if year.len() == 4 => (*year, Some(*month), Some(*day)),
+            [first, second, year] if year.len() == 4 => match order {
+                DateOrder::DayMonth => (*year, Some(*second), Some(*first)),
+                DateOrder::MonthDay => (*year, Some(*first), Some(*second)),
+            },
+            _ => return None,
+        };
+
+        let year = year.parse().ok()?;
+        let month = month.map(str::parse).transpose().ok()?;
+        let day = day.map(str::parse).transpose().ok()?;
+        let precision = match (month, day) {
+            (None, _) => DatePrecision::Year,
+            (Some(_), None) => DatePrecision::Month,
+            (Some(_), Some(_)) => DatePrecision::Day,
+        };
+        let date = NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1))?;
+        Some(Self::new(date, precision))
+    }
+}
+
+/// Shows only what is known, like "2023", "2023-04" or "2023-04-12".
+impl fmt::Display for PartialDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.precision {
+            DatePrecision::Day => write!(f, "{}", self.date.format("%Y-%m-%d")),
+            DatePrecision::Month => write!(f, "{:04}-{:02}", self.date.year(), self.date.month()),
+            DatePrecision::Year => write!(f, "{:04}", self.date.year()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn parse(text: &str, order: DateOrder) -> Option<PartialDate> {
+        PartialDate::parse(text, order, ymd(2023, 4, 12))
+    }
+
+    #[test]
+    fn partial_dates_keep_their_precision() {
+        let year = parse("2023", DateOrder::DayMonth).unwrap();
+        assert_eq!(year, PartialDate::new(ymd(2023, 1, 1), DatePrecision::Year));
+        assert_eq!(year.last_day(), ymd(2023, 12, 31));
+        assert_eq!(year.to_string(), "2023");
+
+        let month = parse("2024-02", DateOrder::DayMonth).unwrap();
+        assert_eq!(
+            month,
+            PartialDate::new(ymd(2024, 2, 1), DatePrecision::Month)
+        );
+        assert_eq!(month.last_day(), ymd(2024, 2, 29));
+        assert_eq!(month.to_string(), "2024-02");
+
+        let day = parse("2023-04-12", DateOrder::DayMonth).unwrap();
+        assert_eq!(day.last_day(), ymd(2023, 4, 12));
+        assert_eq!(day.to_string(), "2023-04-12");
+    }
+
+    #[test]
+    fn day_and_month_follow_the_date_order() {
+        assert_eq!(
+            parse("12/04/2023", DateOrder::DayMonth).unwrap().date,
+            ymd(2023, 4, 12)
+        );
+        assert_eq!(
+            parse("12/04/2023", DateOrder::MonthDay).unwrap().date,
+            ymd(2023, 12, 4)
+        );
+        assert_eq!(parse("13/04/2023", DateOrder::MonthDay), None);
+        // A leading year doesn't depend on the order
+        assert_eq!(
+            parse("2023-04-12", DateOrder::MonthDay).unwrap().date,
+            ymd(2023, 4, 12)
+        );
+    }
+
+    #[test]
+    fn relative_dates_count_back_from_today() {
+        let day = |text| parse(text, DateOrder::DayMonth).map(|date| date.date);
+        assert_eq!(day("Today"), Some(ymd(2023, 4, 12)));
+        assert_eq!(day("yesterday"), Some(ymd(2023, 4, 11)));
+        assert_eq!(day(" last week "), Some(ymd(2023, 4, 5)));
+    }
+
+    #[test]
+    fn other_text_is_not_a_date() {
+        for text in [
+            "",
+            "soon",
+            "23",
+            "2023-",
+            "2023-13",
+            "2023-02-30",
+            "12/04/23",
+        ] {
+            assert_eq!(parse(text, DateOrder::DayMonth), None, "{}", text);
+        }
+    }
+}
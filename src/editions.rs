@@ -0,0 +1,157 @@
+use crate::error::{BookyError, BookyResult};
+
+/// Formats a book can be read in, see [`parse_format`].
+pub const FORMATS: [&str; 4] = ["hardcover", "paperback", "ebook", "audiobook"];
+
+/// Checks an ISBN-10 or ISBN-13 and returns it as ISBN-13.
+///
+/// Dashes and spaces are ignored, so "0-441-17271-7" and "978 0441172719"
+/// are the same book. ISBN-10s are converted so every edition is stored
+/// the same way.
+pub fn parse_isbn(text: &str) -> BookyResult<String> {
+    let isbn: String = text
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let valid = match isbn.len() {
+        10 => is_valid_isbn10(&isbn),
+        13 => is_valid_isbn13(&isbn),
+        _ => {
+            return Err(BookyError::Validation(format!(
+                "An ISBN has 10 or 13 digits, got \"{}\"",
+                text.trim()
+            )))
+        }
+    };
+    if !valid {
+        return Err(BookyError::Validation(format!(
+            "\"{}\" is not a valid ISBN, check for typos",
+            text.trim()
+        )));
+    }
+
+    Ok(match isbn.len() {
+        10 => isbn10_to_isbn13(&isbn),
+        _ => isbn,
+    })
+}
+
+/// Converts a checked ISBN-10 to its ISBN-13, which starts with 978.
+pub fn isbn10_to_isbn13(isbn10: &str) -> String {
+    let body = format!("978{}", &isbn10[..9]);
+    let check = isbn13_check_digit(&body);
+    format!("{}{}", body, check)
+}
+
+/// Converts a checked ISBN-13 to its ISBN-10.
+///
+/// Only ISBN-13s that start with 978 have one.
+pub fn isbn13_to_isbn10(isbn13: &str) -> Option<String> {
+    let body = isbn13.strip_prefix("978")?.get(..9)?;
+    let sum: u32 = digits(body)
+        .zip((2..=10).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    let check = match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10)?,
+    };
+    Some(format!("{}{}", body, check))
+}
+
+/// Checks a typed format, like "Hardcover" or "e-book", and returns it as one of [`FORMATS`].
+pub fn parse_format(text: &str) -> BookyResult<String> {
+    let format = text.trim().to_lowercase().replace([' ', '-'], "");
+    match format.as_str() {
+        "hardcover" | "hardback" => Ok("hardcover".to_string()),
+        "paperback" | "softcover" => Ok("paperback".to_string()),
+        "ebook" | "kindle" | "epub" | "digital" => Ok("ebook".to_string()),
+        "audiobook" | "audio" => Ok("audiobook".to_string()),
+        _ => Err(BookyError::Validation(format!(
+            "Format must be one of {}, got \"{}\"",
+            FORMATS.join(", "),
+            text.trim()
+        ))),
+    }
+}
+
+// `u32::is_multiple_of` needs Rust 1.87, `%` builds on older compilers
+#[allow(clippy::manual_is_multiple_of)]
+fn is_valid_isbn10(isbn: &str) -> bool {
+    if !isbn.is_ascii() {
+        return false;
+    }
+    // Only the check digit can be an X, it stands for 10
+    let (body, check) = isbn.split_at(9);
+    let check = match check {
+        "X" => 10,
+        check => match check.parse::<u32>() {
+            Ok(check) => check,
+            Err(_) => return false,
+        },
+    };
+    if !body.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let sum: u32 = digits(body)
+        .chain([check])
+        .zip((1..=10).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    sum % 11 == 0
+}
+
+fn is_valid_isbn13(isbn: &str) -> bool {
+    isbn.chars().all(|c| c.is_ascii_digit())
+        && isbn13_check_digit(&isbn[..12]) == isbn.chars().last().unwrap_or_default()
+}
+
+// The check digit of the first 12 digits, weighted 1, 3, 1, 3, ...
+fn isbn13_check_digit(body: &str) -> char {
+    let sum: u32 = digits(body)
+        .zip([1, 3].into_iter().cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+}
+
+fn digits(text: &str) -> impl Iterator<Item = u32> + '_ {
+    text.chars().filter_map(|c| c.to_digit(10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isbn10s_are_checked_and_stored_as_isbn13() {
+        assert_eq!(parse_isbn("0-441-17271-7").unwrap(), "9780441172719");
+        assert_eq!(parse_isbn("080442957x").unwrap(), "9780804429573");
+        assert!(parse_isbn("0-441-17271-8").is_err());
+        assert!(parse_isbn("04411727X7").is_err());
+    }
+
+    #[test]
+    fn isbn13s_are_checked() {
+        assert_eq!(parse_isbn("978 0441172719").unwrap(), "9780441172719");
+        assert!(parse_isbn("9780441172710").is_err());
+        assert!(parse_isbn("978044117271").is_err());
+    }
+
+    #[test]
+    fn isbns_convert_both_ways() {
+        assert_eq!(isbn10_to_isbn13("0441172717"), "9780441172719");
+        assert_eq!(
+            isbn13_to_isbn10("9780441172719").as_deref(),
+            Some("0441172717")
+        );
+        assert_eq!(
+            isbn13_to_isbn10("9780804429573").as_deref(),
+            Some("080442957X")
+        );
+        assert_eq!(isbn13_to_isbn10("9791032300824"), None);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{error, fmt, io};
+
+/// Result type used by the database layer and everything built on top of it.
+pub type BookyResult<T> = std::result::Result<T, BookyError>;
+
+/// Everything that can go wrong in booky.
+#[derive(Debug)]
+pub enum BookyError {
+    /// A query failed.
+    Database(diesel::result::Error),
+    /// The database could not be opened.
+    Connection(diesel::ConnectionError),
+    /// Running or inspecting the migrations failed.
+    Migration(Box<dyn error::Error + Send + Sync>),
+    /// The user gave us something we can't store.
+    Validation(String),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The config file or the database location is invalid.
+    Config(String),
+    /// A file that is imported can't be read.
+    Import(String),
+    /// A change can't be saved in or read from the history.
+    History(String),
+}
+
+impl fmt::Display for BookyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookyError::Database(e) => write!(f, "Database error: {}", e),
+            BookyError::Connection(e) => write!(f, "Could not open the database: {}", e),
+            BookyError::Migration(e) => write!(f, "Migration failed: {}", e),
+            BookyError::Validation(msg) => write!(f, "{}", msg),
+            BookyError::Io(e) => write!(f, "IO error: {}", e),
+            BookyError::Config(msg) => write!(f, "Config error: {}", msg),
+            BookyError::Import(msg) => write!(f, "Import failed: {}", msg),
+            BookyError::History(msg) => write!(f, "History error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for BookyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BookyError::Database(e) => Some(e),
+            BookyError::Connection(e) => Some(e),
+            BookyError::Migration(e) => Some(e.as_ref()),
+            BookyError::Io(e) => Some(e),
+            BookyError::Validation(_)
+            | BookyError::Config(_)
+            | BookyError::Import(_)
+            | BookyError::History(_) => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for BookyError {
+    fn from(e: diesel::result::Error) -> Self {
+        BookyError::Database(e)
+    }
+}
+
+impl From<diesel::ConnectionError> for BookyError {
+    fn from(e: diesel::ConnectionError) -> Self {
+        BookyError::Connection(e)
+    }
+}
+
+impl From<serde_json::Error> for BookyError {
+    fn from(e: serde_json::Error) -> Self {
+        BookyError::Import(e.to_string())
+    }
+}
+
+impl From<csv::Error> for BookyError {
+    fn from(e: csv::Error) -> Self {
+        BookyError::Import(e.to_string())
+    }
+}
+
+impl From<io::Error> for BookyError {
+    fn from(e: io::Error) -> Self {
+        BookyError::Io(e)
+    }
+}
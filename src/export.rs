@@ -0,0 +1,104 @@
+use crate::database::models::{Book, Quote, ReadingDay, ReadingSession};
+use crate::error::BookyResult;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Version of the JSON export format, bump it when the layout changes.
+///
+/// Version 2 added the reading sessions, version 1 only had the dates of
+/// the latest one on every book. Version 3 replaced the genre with tags,
+/// version 4 added the series, version 5 the edition and version 6 limited
+/// the status to the values of [`crate::status::ReadingStatus`]. Version 7
+/// added the precision of the dates and version 8 the quotes and the
+/// reading log.
+pub const EXPORT_VERSION: u32 = 8;
+
+/// A booky JSON export, it contains every book with its id.
+///
+/// Dates are written as `year-month-day` and missing values as `null`,
+/// so importing an export gives back exactly the same library.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryExport {
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    pub books: Vec<Book>,
+    #[serde(default)]
+    pub sessions: Vec<ReadingSession>,
+    #[serde(default)]
+    pub quotes: Vec<Quote>,
+    #[serde(default)]
+    pub reading_log: Vec<ReadingDay>,
+}
+
+impl LibraryExport {
+    pub fn new(
+        books: Vec<Book>,
+        sessions: Vec<ReadingSession>,
+        quotes: Vec<Quote>,
+        reading_log: Vec<ReadingDay>,
+    ) -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            exported_at: Some(chrono::Local::now().to_rfc3339()),
+            books,
+            sessions,
+            quotes,
+            reading_log,
+        }
+    }
+}
+
+/// Columns of the CSV export, in the same order as the fields of [`Book`].
+///
+/// The dates are those of the latest reading session, a date known to the
+/// year is written as January 1st with the precision `year`.
+pub const CSV_COLUMNS: [&str; 18] = [
+    "id",
+    "title",
+    "author",
+    "tags",
+    "rating",
+    "status",
+    "series",
+    "series_index",
+    "isbn",
+    "publisher",
+    "published_year",
+    "pages",
+    "language",
+    "format",
+    "start_date",
+    "end_date",
+    "start_precision",
+    "end_precision",
+];
+
+/// Writes all `books` as CSV with a header row, missing dates are empty cells.
+pub fn write_csv(books: Vec<Book>, writer: impl Write) -> BookyResult<()> {
+    // Write the header ourselves so an empty library still gets one
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    writer.write_record(CSV_COLUMNS)?;
+    for book in &books {
+        writer.serialize(book)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes all `books` with their sessions, quotes and reading log as a
+/// pretty printed JSON document.
+pub fn write_json(
+    books: Vec<Book>,
+    sessions: Vec<ReadingSession>,
+    quotes: Vec<Quote>,
+    reading_log: Vec<ReadingDay>,
+    mut writer: impl Write,
+) -> BookyResult<()> {
+    let export = LibraryExport::new(books, sessions, quotes, reading_log);
+    serde_json::to_writer_pretty(&mut writer, &export)?;
+    writeln!(writer)?;
+    Ok(())
+}
@@ -0,0 +1,574 @@
+use crate::database::models::{Book, NewBook, NewReadingSession, ReadingSession};
+use crate::dates::{DateOrder, PartialDate};
+use crate::editions::{parse_format, parse_isbn};
+use crate::error::{BookyError, BookyResult};
+use crate::series::format_index;
+use crate::status::ReadingStatus;
+use crate::tags::{join_tags, split_tags};
+use chrono::Local;
+use std::fmt;
+
+/// Format of complete dates, the way importers hand them to the form.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The fields of a book as text, the way the user typed them.
+///
+/// Both the add/update popup and the command line fill in a form,
+/// so a book is turned into a [`NewBook`] the same way everywhere.
+#[derive(Debug, Default, Clone)]
+pub struct BookForm {
+    pub title: String,
+    pub author: String,
+    /// Comma separated, like "fantasy, book club".
+    pub tags: String,
+    pub rating: String,
+    /// See [`ReadingStatus::parse`], empty to work it out from the dates.
+    pub status: String,
+    pub series: String,
+    /// Number of the volume in its series, like 3 or 2.5.
+    pub series_index: String,
+    /// ISBN-10 or ISBN-13, with or without dashes.
+    pub isbn: String,
+    pub publisher: String,
+    pub published_year: String,
+    pub pages: String,
+    pub language: String,
+    /// Hardcover, paperback, ebook or audiobook.
+    pub format: String,
+    /// See [`parse_date`] for the dates that are understood.
+    pub start_date: String,
+    pub end_date: String,
+}
+
+impl BookForm {
+    /// Fills in a form with the current values of `book`, used when updating.
+    pub fn from_book(book: &Book) -> Self {
+        Self {
+            title: book.title.clone(),
+            author: book.author.clone(),
+            tags: book.tags.clone(),
+            rating: book.rating.to_string(),
+            status: book.status.to_string(),
+            series: book.series.clone().unwrap_or_default(),
+            series_index: book.series_index.map(format_index).unwrap_or_default(),
+            isbn: book.isbn.clone().unwrap_or_default(),
+            publisher: book.publisher.clone().unwrap_or_default(),
+            published_year: format_number(book.published_year),
+            pages: format_number(book.pages),
+            language: book.language.clone().unwrap_or_default(),
+            format: book.format.clone().unwrap_or_default(),
+            start_date: format_date(book.start()),
+            end_date: format_date(book.end()),
+        }
+    }
+
+    /// The text of `field`, sessions have fields a book doesn't.
+    pub fn value_mut(&mut self, field: FormField) -> Option<&mut String> {
+        let value = match field {
+            FormField::Title => &mut self.title,
+            FormField::Author => &mut self.author,
+            FormField::Tags => &mut self.tags,
+            FormField::Rating => &mut self.rating,
+            FormField::Status => &mut self.status,
+            FormField::Series => &mut self.series,
+            FormField::SeriesIndex => &mut self.series_index,
+            FormField::Isbn => &mut self.isbn,
+            FormField::Publisher => &mut self.publisher,
+            FormField::PublishedYear => &mut self.published_year,
+            FormField::Pages => &mut self.pages,
+            FormField::Language => &mut self.language,
+            FormField::Format => &mut self.format,
+            FormField::StartDate => &mut self.start_date,
+            FormField::EndDate => &mut self.end_date,
+            FormField::Minutes => return None,
+        };
+        Some(value)
+    }
+
+    /// Parses the form into a book that can be stored.
+    ///
+    /// Fails with every problem of [`BookForm::validate`] in one message,
+    /// this is what the command line and the importers use.
+    pub fn to_new_book(&self, order: DateOrder) -> BookyResult<NewBook> {
+        self.validate(order).map_err(BookyError::from)
+    }
+
+    /// Checks every field and parses the form into a book that can be stored.
+    ///
+    /// The title is required and the rating is 0 to 5 stars. An empty rating
+    /// counts as 0, empty dates stay empty and an empty status is worked out
+    /// from the dates. An ISBN-10 is stored as ISBN-13, see [`parse_isbn`].
+    /// Dates like `12/04/2023` are read in `order` and the end date can't be
+    /// before the start date.
+    pub fn validate(&self, order: DateOrder) -> Result<NewBook, FormErrors> {
+        let mut errors = FormErrors::default();
+
+        if self.title.trim().is_empty() {
+            errors.add(FormField::Title, "Title can't be empty");
+        }
+        let rating = errors.check(FormField::Rating, parse_rating(&self.rating));
+
+        let series = non_empty(&self.series);
+        let series_index = errors.check(
+            FormField::SeriesIndex,
+            match self.series_index.trim() {
+                "" => Ok(None),
+                index => index.parse::<f64>().map(Some).map_err(|_| {
+                    BookyError::Validation(format!("Volume must be a number, got \"{}\"", index))
+                }),
+            },
+        );
+        if series.is_none() && series_index.is_some() {
+            errors.add(FormField::SeriesIndex, "A volume number needs a series");
+        }
+
+        let isbn = errors.check(
+            FormField::Isbn,
+            match self.isbn.trim() {
+                "" => Ok(None),
+                isbn => parse_isbn(isbn).map(Some),
+            },
+        );
+        let format = errors.check(
+            FormField::Format,
+            match self.format.trim() {
+                "" => Ok(None),
+                format => parse_format(format).map(Some),
+            },
+        );
+        let published_year = errors.check(
+            FormField::PublishedYear,
+            parse_number(&self.published_year, "Year"),
+        );
+        let pages = errors.check(FormField::Pages, parse_count(&self.pages, "Pages"));
+
+        let start = errors.check(
+            FormField::StartDate,
+            parse_date(&self.start_date, "Start date", order),
+        );
+        let end = errors.check(
+            FormField::EndDate,
+            parse_date(&self.end_date, "End date", order),
+        );
+        errors.check(FormField::EndDate, check_dates(start, end));
+        let start_date = start.map(|start| start.date);
+        let end_date = end.map(|end| end.date);
+        let status = errors.check(
+            FormField::Status,
+            match self.status.trim() {
+                "" => Ok(ReadingStatus::from_dates(start_date, end_date)),
+                status => status.parse(),
+            },
+        );
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(NewBook {
+            title: self.title.trim().to_string(),
+            author: self.author.trim().to_string(),
+            tags: join_tags(&split_tags(&self.tags)),
+            rating,
+            status,
+            series,
+            series_index,
+            isbn,
+            publisher: non_empty(&self.publisher),
+            published_year,
+            pages,
+            language: non_empty(&self.language),
+            format,
+            start_date,
+            end_date,
+            start_precision: start.map(|start| start.precision).unwrap_or_default(),
+            end_precision: end.map(|end| end.precision).unwrap_or_default(),
+        })
+    }
+}
+
+/// The fields of a reading session as text, see [`BookForm`].
+#[derive(Debug, Default, Clone)]
+pub struct SessionForm {
+    /// See [`parse_date`] for the dates that are understood.
+    pub started: String,
+    pub finished: String,
+    /// The book was put down before the end.
+    pub abandoned: bool,
+    pub pages: String,
+    pub minutes: String,
+}
+
+impl SessionForm {
+    /// Checks every field and parses the form into a session of `book_id`.
+    ///
+    /// The end can't be before the start, like for a book, and pages and
+    /// minutes must be more than 0 when they are given.
+    pub fn to_new_session(&self, book_id: i32, order: DateOrder) -> BookyResult<NewReadingSession> {
+        let mut errors = FormErrors::default();
+
+        let started = errors.check(
+            FormField::StartDate,
+            parse_date(&self.started, "Start date", order),
+        );
+        let finished = errors.check(
+            FormField::EndDate,
+            parse_date(&self.finished, "End date", order),
+        );
+        errors.check(FormField::EndDate, check_dates(started, finished));
+        let pages = errors.check(FormField::Pages, parse_count(&self.pages, "Pages"));
+        let minutes = errors.check(FormField::Minutes, parse_count(&self.minutes, "Minutes"));
+
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        let outcome = match (self.abandoned, finished) {
+            (true, _) => Some(ReadingSession::ABANDONED),
+            (false, Some(_)) => Some(ReadingSession::FINISHED),
+            (false, None) => None,
+        };
+        Ok(NewReadingSession {
+            book_id,
+            started: started.map(|started| started.date),
+            finished: finished.map(|finished| finished.date),
+            outcome: outcome.map(str::to_string),
+            pages,
+            minutes,
+            started_precision: started.map(|started| started.precision).unwrap_or_default(),
+            finished_precision: finished
+                .map(|finished| finished.precision)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Checks that `end` isn't before `start`, either can be missing.
+///
+/// Partial dates overlap, "2023" can end in April 2023.
+pub fn check_dates(start: Option<PartialDate>, end: Option<PartialDate>) -> BookyResult<()> {
+    match (start, end) {
+        (Some(start), Some(end)) if end.last_day() < start.date => Err(BookyError::Validation(
+            "End date is before the start date".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A field of [`BookForm`] or [`SessionForm`], errors are shown next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormField {
+    Title,
+    Author,
+    Tags,
+    Rating,
+    Status,
+    Series,
+    SeriesIndex,
+    Isbn,
+    Publisher,
+    PublishedYear,
+    Pages,
+    Language,
+    Format,
+    StartDate,
+    EndDate,
+    Minutes,
+}
+
+/// Everything that is wrong with a form, at most one message per field.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FormErrors {
+    errors: Vec<(FormField, String)>,
+}
+
+impl FormErrors {
+    /// Adds the error of `field`, the first error of a field is kept.
+    pub fn add(&mut self, field: FormField, message: impl Into<String>) {
+        if self.get(field).is_none() {
+            self.errors.push((field, message.into()));
+        }
+    }
+
+    /// Returns the value of `result`, or adds its error to `field` and returns the default.
+    fn check<T: Default>(&mut self, field: FormField, result: BookyResult<T>) -> T {
+        result.unwrap_or_else(|e| {
+            self.add(field, e.to_string());
+            T::default()
+        })
+    }
+
+    pub fn get(&self, field: FormField) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(error_field, _)| *error_field == field)
+            .map(|(_, message)| message.as_str())
+    }
+
+    /// Every field with an error and its message, in the order they were found.
+    pub fn iter(&self) -> impl Iterator<Item = (FormField, &str)> {
+        self.errors
+            .iter()
+            .map(|(field, message)| (*field, message.as_str()))
+    }
+
+    /// Keeps the errors of the fields for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(FormField) -> bool) {
+        self.errors.retain(|(field, _)| keep(*field));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+}
+
+impl fmt::Display for FormErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self
+            .errors
+            .iter()
+            .map(|(_, message)| message.as_str())
+            .collect();
+        f.write_str(&messages.join(", "))
+    }
+}
+
+impl From<FormErrors> for BookyError {
+    fn from(errors: FormErrors) -> Self {
+        BookyError::Validation(errors.to_string())
+    }
+}
+
+// Trimmed, an empty field is None
+fn non_empty(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Parses 0 to 5 stars, an empty field is 0.
+pub fn parse_rating(value: &str) -> BookyResult<i32> {
+    let rating = value.trim();
+    if rating.is_empty() {
+        return Ok(0);
+    }
+    match rating.parse::<i32>() {
+        Ok(stars @ 0..=5) => Ok(stars),
+        Ok(_) => Err(BookyError::Validation(format!(
+            "Rating must be 0 to 5 stars, got {}",
+            rating
+        ))),
+        Err(_) => Err(BookyError::Validation(format!(
+            "Rating must be a number, got \"{}\"",
+            rating
+        ))),
+    }
+}
+
+fn parse_number(value: &str, field: &str) -> BookyResult<Option<i32>> {
+    match value.trim() {
+        "" => Ok(None),
+        number => number.parse::<i32>().map(Some).map_err(|_| {
+            BookyError::Validation(format!("{} must be a number, got \"{}\"", field, number))
+        }),
+    }
+}
+
+// Like `parse_number`, but the number must be more than 0
+fn parse_count(value: &str, field: &str) -> BookyResult<Option<i32>> {
+    match parse_number(value, field)? {
+        Some(count) if count <= 0 => Err(BookyError::Validation(format!(
+            "{} must be more than 0",
+            field
+        ))),
+        count => Ok(count),
+    }
+}
+
+fn format_number(number: Option<i32>) -> String {
+    number.map(|number| number.to_string()).unwrap_or_default()
+}
+
+/// Formats a date the way it is typed, a missing date is an empty string.
+///
+/// Only the known part is shown, a date known to the year is just "2023".
+pub fn format_date(date: Option<PartialDate>) -> String {
+    date.map(|date| date.to_string()).unwrap_or_default()
+}
+
+/// Formats a date to be shown in a table, a missing date is a dash.
+pub fn display_date(date: Option<PartialDate>) -> String {
+    match date {
+        Some(_) => format_date(date),
+        None => "—".to_string(),
+    }
+}
+
+/// Parses a typed date, `field` names it in the error.
+///
+/// An empty value is `None`, the book doesn't have that date. See
+/// [`PartialDate::parse`] for what is understood, `today`, `yesterday` and
+/// `last week` count back from the current day.
+pub fn parse_date(value: &str, field: &str, order: DateOrder) -> BookyResult<Option<PartialDate>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let today = Local::now().date_naive();
+    PartialDate::parse(value, order, today)
+        .map(Some)
+        .ok_or_else(|| {
+            let example = match order {
+                DateOrder::DayMonth => "12/04/2023",
+                DateOrder::MonthDay => "04/12/2023",
+            };
+            BookyError::Validation(format!(
+                "{} must be a date like 2023-04-12, {}, 2023-04, 2023 or today, got \"{}\"",
+                field, example, value
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dune() -> BookForm {
+        BookForm {
+            title: "Dune".to_string(),
+            author: "Frank Herbert".to_string(),
+            ..BookForm::default()
+        }
+    }
+
+    fn error(form: BookForm, field: FormField) -> Option<String> {
+        let errors = form.validate(DateOrder::default()).unwrap_err();
+        errors.get(field).map(str::to_string)
+    }
+
+    #[test]
+    fn a_book_needs_a_title() {
+        let form = BookForm {
+            title: "   ".to_string(),
+            ..dune()
+        };
+        assert_eq!(
+            error(form, FormField::Title).as_deref(),
+            Some("Title can't be empty")
+        );
+    }
+
+    #[test]
+    fn ratings_are_0_to_5_stars() {
+        for (rating, message) in [
+            ("abc", "Rating must be a number, got \"abc\""),
+            ("6", "Rating must be 0 to 5 stars, got 6"),
+            ("-1", "Rating must be 0 to 5 stars, got -1"),
+        ] {
+            let form = BookForm {
+                rating: rating.to_string(),
+                ..dune()
+            };
+            assert_eq!(error(form, FormField::Rating).as_deref(), Some(message));
+        }
+        let form = BookForm {
+            rating: " 5 ".to_string(),
+            ..dune()
+        };
+        assert_eq!(form.to_new_book(DateOrder::default()).unwrap().rating, 5);
+        assert_eq!(dune().to_new_book(DateOrder::default()).unwrap().rating, 0);
+    }
+
+    #[test]
+    fn the_end_date_is_not_before_the_start_date() {
+        let form = BookForm {
+            start_date: "2023-04-12".to_string(),
+            end_date: "2023-04-11".to_string(),
+            ..dune()
+        };
+        assert_eq!(
+            error(form, FormField::EndDate).as_deref(),
+            Some("End date is before the start date")
+        );
+
+        // The end date is compared with its last day
+        let form = BookForm {
+            start_date: "2023-04-12".to_string(),
+            end_date: "2023-04".to_string(),
+            ..dune()
+        };
+        let book = form.to_new_book(DateOrder::default()).unwrap();
+        assert_eq!(book.status, ReadingStatus::Finished);
+    }
+
+    #[test]
+    fn pages_are_more_than_0() {
+        for pages in ["0", "-3"] {
+            let form = BookForm {
+                pages: pages.to_string(),
+                ..dune()
+            };
+            assert_eq!(
+                error(form, FormField::Pages).as_deref(),
+                Some("Pages must be more than 0")
+            );
+        }
+    }
+
+    #[test]
+    fn a_bad_isbn_is_an_error_of_its_field() {
+        let form = BookForm {
+            isbn: "0-441-17271-8".to_string(),
+            rating: "9".to_string(),
+            ..dune()
+        };
+        let errors = form.validate(DateOrder::default()).unwrap_err();
+        assert_eq!(
+            errors.get(FormField::Isbn),
+            Some("\"0-441-17271-8\" is not a valid ISBN, check for typos")
+        );
+        // Every problem is reported at once
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn title_and_author_are_stored_trimmed() {
+        let form = BookForm {
+            title: "  Dune ".to_string(),
+            author: " Frank Herbert\t".to_string(),
+            ..BookForm::default()
+        };
+        let book = form.to_new_book(DateOrder::default()).unwrap();
+        assert_eq!(book.title, "Dune");
+        assert_eq!(book.author, "Frank Herbert");
+    }
+
+    #[test]
+    fn sessions_end_after_they_start_and_count_up() {
+        let form = SessionForm {
+            started: "2024-05-10".to_string(),
+            finished: "2024-01-01".to_string(),
+            pages: "-5".to_string(),
+            minutes: "0".to_string(),
+            ..SessionForm::default()
+        };
+        let error = form.to_new_session(1, DateOrder::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "End date is before the start date, Pages must be more than 0, Minutes must be more than 0"
+        );
+
+        // A session finished "2024" may have started in May
+        let form = SessionForm {
+            started: "2024-05-10".to_string(),
+            finished: "2024".to_string(),
+            pages: "120".to_string(),
+            ..SessionForm::default()
+        };
+        let session = form.to_new_session(1, DateOrder::default()).unwrap();
+        assert_eq!(session.pages, Some(120));
+        assert_eq!(session.minutes, None);
+        assert_eq!(session.outcome.as_deref(), Some(ReadingSession::FINISHED));
+    }
+}
@@ -1,15 +1,15 @@
 use crate::app::{
-    App, AppResult, BookEditFocus, BookState, SearchFieldFocus, SearchState, EDIT_WINDOW_FOCUS,
-    SEARCH_WINDOW_FOCUS,
+    App, AppResult, BookEditFocus, BookState, SearchFieldFocus, SearchState, SeriesState,
+    TrashState, EDIT_WINDOW_FOCUS, SEARCH_WINDOW_FOCUS,
 };
-use crate::database;
-use crate::database::models::NewBook;
-use chrono::{Local, NaiveDate};
+use crate::error::{BookyError, BookyResult};
+use crate::form::BookForm;
+use crate::status::ReadingStatus;
+use crate::tags::accept_tag;
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use int_enum::IntEnum;
 use std::error;
-
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
 
 // This function allows us to change the focus when pressing tab in the add/update menu
 pub fn change_add_focus(
@@ -21,7 +21,7 @@ pub fn change_add_focus(
     } else {
         let mut current_value = (task.focus.int_value() - 1) % EDIT_WINDOW_FOCUS;
         if current_value < 0 {
-            current_value = 8;
+            current_value = EDIT_WINDOW_FOCUS - 1;
         }
         current_value
     };
@@ -39,7 +39,7 @@ pub fn change_search_focus(
     } else {
         let mut current_value = (task.focus.int_value() - 1) % SEARCH_WINDOW_FOCUS;
         if current_value < 0 {
-            current_value = 8;
+            current_value = SEARCH_WINDOW_FOCUS - 1;
         }
         current_value
     };
@@ -47,51 +47,98 @@ pub fn change_search_focus(
     Ok(())
 }
 
+// Creates or updates the book described by the add/update popup
+fn save_book(app: &mut App, task: &BookState<'_>) -> BookyResult<String> {
+    let new_book = task.to_form().to_new_book(app.date_order)?;
+
+    let message = if !task.is_edit {
+        let book = app.library.create_book(&new_book)?;
+        format!("Added \"{}\"", book.title)
+    } else if let Some(current_id) = app.selected_book().map(|book| book.id) {
+        let book = app.library.update_book(current_id, &new_book)?;
+        format!("Updated \"{}\"", book.title)
+    } else {
+        return Err(BookyError::Validation(
+            "No book selected to update".to_string(),
+        ));
+    };
+    app.reload()?;
+    Ok(message)
+}
+
+// The search status goes from any status (None) through every single one
+fn cycle_search_status(status: Option<ReadingStatus>, forward: bool) -> Option<ReadingStatus> {
+    let choices: Vec<Option<ReadingStatus>> = std::iter::once(None)
+        .chain(ReadingStatus::ALL.map(Some))
+        .collect();
+    let len = choices.len();
+    let index = choices
+        .iter()
+        .position(|choice| *choice == status)
+        .unwrap_or_default();
+    if forward {
+        choices[(index + 1) % len]
+    } else {
+        choices[(index + len - 1) % len]
+    }
+}
+
+// Replaces the tag being typed with the first suggestion, returns false when there is none
+fn complete_tags(task: &mut BookState<'_>, known: &[String]) -> bool {
+    let (row, column) = task.tags.cursor();
+    let line = &task.tags.lines()[row];
+    if row + 1 != task.tags.lines().len() || column != line.chars().count() {
+        return false;
+    }
+    let Some(tag) = task
+        .tag_suggestions(known)
+        .first()
+        .map(|tag| tag.to_string())
+    else {
+        return false;
+    };
+
+    let text = accept_tag(&task.tags.lines().join("\n"), &tag);
+    task.tags = TextArea::from(text.lines());
+    task.tags.move_cursor(CursorMove::End);
+    true
+}
+
 pub fn handle_add_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
     let updated_task = if let Some(mut task) = app.book_edit_state.take() {
         match (key_event.code, task.focus) {
             (KeyCode::Tab, _) => {
+                task.check_focused(app.date_order);
                 change_add_focus(&mut task, true)?;
                 Some(task)
             }
             (KeyCode::BackTab, _) => {
+                task.check_focused(app.date_order);
                 change_add_focus(&mut task, false)?;
                 Some(task)
             }
             (KeyCode::Enter, BookEditFocus::ConfirmBtn) => {
-                let title = task.title.into_lines().join("\n");
-                let author = task.author.into_lines().join("\n");
-                let genre = task.genre.into_lines().join("\n");
-                let status = task.status.into_lines().join("\n");
-                let start_date = task.start_date.into_lines().join("\n");
-                let end_date = task.end_date.into_lines().join("\n");
-                let rating = task.rating.lines()[0].parse::<i32>().unwrap_or_default();
-
-                let default_date = Local::now().naive_local();
-                let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
-                    .unwrap_or(default_date.into());
-                let end_date =
-                    NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").unwrap_or(default_date.into());
-
-                let new_book = NewBook {
-                    title,
-                    author,
-                    genre,
-                    rating,
-                    status,
-                    start_date: Some(start_date),
-                    end_date: Some(end_date),
-                };
-                if !task.is_edit {
-                    database::create_book(new_book);
+                task.validate(app.date_order);
+                if !task.errors.is_empty() {
+                    app.log_error(format!(
+                        "Can't save yet, fix the {} marked field(s)",
+                        task.errors.len()
+                    ));
+                    Some(task)
                 } else {
-                    if let Some(selected) = app.state.selected() {
-                        let current_id = app.items.get(selected).unwrap().id;
-                        database::update_book(current_id, new_book);
+                    match save_book(app, &task) {
+                        Ok(message) => {
+                            app.log_info(message);
+                            app.add_popup = !app.add_popup;
+                            None
+                        }
+                        // Keep the popup open so nothing the user typed is lost
+                        Err(e) => {
+                            app.log_error(e);
+                            Some(task)
+                        }
                     }
                 }
-                app.add_popup = !app.add_popup;
-                None
             }
             (KeyCode::Enter, BookEditFocus::CancelBtn) => {
                 app.add_popup = !app.add_popup;
@@ -106,16 +153,56 @@ pub fn handle_add_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
                 task.author.input(key_event);
                 Some(task)
             }
-            (_, BookEditFocus::Genre) => {
-                task.genre.input(key_event);
+            // Right at the end of the line takes the first suggested tag
+            (KeyCode::Right, BookEditFocus::Tags) if complete_tags(&mut task, &app.tags) => {
+                Some(task)
+            }
+            (_, BookEditFocus::Tags) => {
+                task.tags.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Series) => {
+                task.series.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::SeriesIndex) => {
+                task.series_index.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Isbn) => {
+                task.isbn.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Format) => {
+                task.format.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Publisher) => {
+                task.publisher.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::PublishedYear) => {
+                task.published_year.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Language) => {
+                task.language.input(key_event);
+                Some(task)
+            }
+            (_, BookEditFocus::Pages) => {
+                task.pages.input(key_event);
                 Some(task)
             }
             (_, BookEditFocus::Rating) => {
                 task.rating.input(key_event);
                 Some(task)
             }
-            (_, BookEditFocus::Status) => {
-                task.status.input(key_event);
+            (KeyCode::Right | KeyCode::Char(' ') | KeyCode::Enter, BookEditFocus::Status) => {
+                task.status = task.status.next();
+                Some(task)
+            }
+            (KeyCode::Left, BookEditFocus::Status) => {
+                task.status = task.status.previous();
                 Some(task)
             }
             (_, BookEditFocus::StartDate) => {
@@ -155,16 +242,27 @@ pub fn handle_search_events(key_event: KeyEvent, app: &mut App) -> AppResult<()>
                 task.author.input(key_event);
                 Some(task)
             }
-            (_, SearchFieldFocus::Genre) => {
-                task.genre.input(key_event);
+            (_, SearchFieldFocus::Tags) => {
+                task.tags.input(key_event);
+                Some(task)
+            }
+            (
+                KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right,
+                SearchFieldFocus::TagMatch,
+            ) => {
+                task.tag_match = task.tag_match.toggle();
                 Some(task)
             }
             (_, SearchFieldFocus::Rating) => {
                 task.rating.input(key_event);
                 Some(task)
             }
-            (_, SearchFieldFocus::Status) => {
-                task.status.input(key_event);
+            (KeyCode::Right | KeyCode::Char(' ') | KeyCode::Enter, SearchFieldFocus::Status) => {
+                task.status = cycle_search_status(task.status, true);
+                Some(task)
+            }
+            (KeyCode::Left, SearchFieldFocus::Status) => {
+                task.status = cycle_search_status(task.status, false);
                 Some(task)
             }
             (_, SearchFieldFocus::StartDate) => {
@@ -175,45 +273,18 @@ pub fn handle_search_events(key_event: KeyEvent, app: &mut App) -> AppResult<()>
                 task.end_date.input(key_event);
                 Some(task)
             }
-            (KeyCode::Enter, SearchFieldFocus::ConfirmBtn) => {
-                let title = task.title.into_lines().join("\n");
-                let author = task.author.into_lines().join("\n");
-                let genre = task.genre.into_lines().join("\n");
-                let rating = task.rating.lines()[0].parse::<i32>().unwrap_or_default();
-                let status = task.status.into_lines().join("\n");
-                let start_date = task.start_date.into_lines().join("\n");
-                let end_date = task.end_date.into_lines().join("\n");
-
-                // If the user doesn't provide a date to search/filter
-                // default to searching for a book that has a startdate of > 1500
-                // end an end date of 4050 <
-                // Startdate doesn't mean releasedate, instead it means the date you read
-                // the book, the reason I do this is because other wise it doesn't filter
-                // correctly in database.rs
-                // If you are reading this from 4050 I'm sorry.
-
-                let default_start_date = NaiveDate::from_ymd_opt(1500, 1, 1).unwrap();
-                let default_end_date = NaiveDate::from_ymd_opt(4050, 1, 1).unwrap();
-                let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
-                    .unwrap_or(default_start_date.into());
-                let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
-                    .unwrap_or(default_end_date.into());
-
-                let book_info = NewBook {
-                    title,
-                    author,
-                    genre,
-                    rating,
-                    status,
-                    start_date: Some(start_date),
-                    end_date: Some(end_date),
-                };
-
-                app.items = database::search_book(book_info);
-                app.search_active = true;
-                app.search_popup = !app.search_popup;
-                None
-            }
+            (KeyCode::Enter, SearchFieldFocus::ConfirmBtn) => match task.to_query(app.date_order) {
+                Ok(book_info) => {
+                    app.search_popup = !app.search_popup;
+                    app.search(book_info, task.tag_match)?;
+                    None
+                }
+                // Keep the popup open so the date or rating can be fixed
+                Err(e) => {
+                    app.log_error(e);
+                    Some(task)
+                }
+            },
             (KeyCode::Enter, SearchFieldFocus::CancelBtn) => {
                 app.search_popup = !app.search_popup;
                 None
@@ -227,6 +298,101 @@ pub fn handle_search_events(key_event: KeyEvent, app: &mut App) -> AppResult<()>
     Ok(())
 }
 
+pub fn handle_series_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    let Some(mut state) = app.series_state.take() else {
+        return Ok(());
+    };
+    let result = match key_event.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('s') => {
+            app.series_popup = !app.series_popup;
+            return Ok(());
+        }
+        KeyCode::Up | KeyCode::Char('k') => state.cycle(&mut app.library, false),
+        KeyCode::Down | KeyCode::Char('j') => state.cycle(&mut app.library, true),
+        _ => Ok(()),
+    };
+    // Keep the popup open when loading a series failed
+    app.series_state = Some(state);
+    Ok(result?)
+}
+
+pub fn handle_delete_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match key_event.code {
+        KeyCode::Char('y') | KeyCode::Enter => {
+            app.delete_popup = false;
+            if let Some(book) = app.selected_book() {
+                let (current_id, message) = (
+                    book.id,
+                    format!("Moved \"{}\" to the trash, press t to see it", book.title),
+                );
+                app.library.delete_book(current_id)?;
+                app.reload()?;
+                app.log_info(message);
+            }
+        }
+        KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => {
+            app.delete_popup = false;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn handle_trash_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    let Some(mut state) = app.trash_state.take() else {
+        return Ok(());
+    };
+    // Purged books are only brought back by undo, so any key but y cancels it
+    if state.confirm_purge {
+        state.confirm_purge = false;
+        let selected = state.selected().map(|book| (book.id, book.title.clone()));
+        let result = match (key_event.code, selected) {
+            (KeyCode::Char('y'), Some((book_id, title))) => {
+                app.library.purge_books(&[book_id]).and_then(|_| {
+                    state.reload(&mut app.library)?;
+                    app.log_info(format!("Purged \"{}\" for good", title));
+                    Ok(())
+                })
+            }
+            _ => Ok(()),
+        };
+        app.trash_state = Some(state);
+        return Ok(result?);
+    }
+
+    let result = match key_event.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('t') => {
+            app.trash_popup = !app.trash_popup;
+            return Ok(());
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            state.cycle(false);
+            Ok(())
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            state.cycle(true);
+            Ok(())
+        }
+        KeyCode::Char('r') => match state.selected().map(|book| book.id) {
+            Some(book_id) => app.library.restore_book(book_id).and_then(|book| {
+                state.reload(&mut app.library)?;
+                app.reload()?;
+                app.log_info(format!("Restored \"{}\"", book.title));
+                Ok(())
+            }),
+            None => Ok(()),
+        },
+        KeyCode::Char('p') => {
+            state.confirm_purge = state.selected().is_some();
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    // Keep the popup open when restoring failed
+    app.trash_state = Some(state);
+    Ok(result?)
+}
+
 pub fn handle_main_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
     match key_event.code {
         // Exit application on `ESC` or `q`
@@ -234,61 +400,64 @@ pub fn handle_main_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
             app.quit();
         }
         // Exit application on `Ctrl-C`
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.quit();
-            }
+        KeyCode::Char('c') | KeyCode::Char('C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.quit();
+        }
+        // Undo and redo the latest change to the books
+        KeyCode::Char('z') if key_event.modifiers == KeyModifiers::CONTROL => {
+            let message = match app.library.undo()? {
+                Some(entry) => format!("Undid: {}, press ctrl-r to redo it", entry.description),
+                None => "Nothing to undo".to_string(),
+            };
+            app.reload()?;
+            app.log_info(message);
         }
-        // Remove book
+        KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => {
+            let message = match app.library.redo()? {
+                Some(entry) => format!("Redid: {}", entry.description),
+                None => "Nothing to redo".to_string(),
+            };
+            app.reload()?;
+            app.log_info(message);
+        }
+        // Move the book to the trash once the user confirms it
         KeyCode::Char('d') => {
-            if app.items.len() != 0 {
-                database::delete_book(app);
-            }
+            app.delete_popup = app.selected_book().is_some();
+        }
+        KeyCode::Char('t') => {
+            app.trash_state = Some(TrashState::load(&mut app.library)?);
+            app.trash_popup = !app.trash_popup;
         }
         KeyCode::Char('u') => {
-            // Do this in app later?
-            if let Some(index) = app.state.selected() {
-                let current_book = app.items.get(index).unwrap();
-                let new_book_state = BookState {
-                    title: TextArea::from(current_book.title.lines()),
-                    author: TextArea::from(current_book.author.lines()),
-                    genre: TextArea::from(current_book.genre.lines()),
-                    rating: TextArea::from(current_book.rating.to_string().lines()),
-                    status: TextArea::from(current_book.status.lines()),
-                    start_date: TextArea::from(
-                        current_book.start_date.unwrap().to_string().lines(),
-                    ),
-                    end_date: TextArea::from(current_book.end_date.unwrap().to_string().lines()),
-                    focus: BookEditFocus::Title,
-                    is_edit: true,
-                };
-                app.book_edit_state = Some(new_book_state);
+            if let Some(current_book) = app.selected_book() {
+                let form = BookForm::from_book(current_book);
+                app.book_edit_state = Some(BookState::from_form(form, true));
+                app.add_popup = !app.add_popup;
             }
-            app.add_popup = !app.add_popup;
         }
         KeyCode::Char('i') => {
             app.book_edit_state = Some(BookState::default());
-            app.search_active = false;
+            app.clear_search()?;
             app.add_popup = !app.add_popup;
         }
         // Clear search query
         KeyCode::Char('r') => {
-            app.search_active = false;
+            app.clear_search()?;
         }
-        KeyCode::Up | KeyCode::Char('k') => {
-            if app.items.len() != 0 {
-                app.previous();
-            }
+        KeyCode::Up | KeyCode::Char('k') if !app.items.is_empty() => {
+            app.previous();
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            if app.items.len() != 0 {
-                app.next();
-            }
+        KeyCode::Down | KeyCode::Char('j') if !app.items.is_empty() => {
+            app.next();
         }
         KeyCode::Char('/') => {
             app.search_field_state = Some(SearchState::default());
             app.search_popup = !app.search_popup;
         }
+        KeyCode::Char('s') => {
+            app.series_state = Some(SeriesState::load(&mut app.library)?);
+            app.series_popup = !app.series_popup;
+        }
         KeyCode::Char('?') => {
             app.help_popup = !app.help_popup;
         }
@@ -302,13 +471,23 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
     // Only handle Press or Repeat events, ignore Release events
     // Without this check terminal will register two events for each key press on some platforms
     if key_event.kind == KeyEventKind::Press || key_event.kind == KeyEventKind::Repeat {
-        if app.book_edit_state.is_some() {
-            handle_add_events(key_event, app).expect("Failed to handle events related to adding");
+        let result = if app.book_edit_state.is_some() {
+            handle_add_events(key_event, app)
         } else if app.search_field_state.is_some() {
             handle_search_events(key_event, app)
-                .expect("Failed to handle events related to searching");
+        } else if app.series_state.is_some() {
+            handle_series_events(key_event, app)
+        } else if app.delete_popup {
+            handle_delete_events(key_event, app)
+        } else if app.trash_state.is_some() {
+            handle_trash_events(key_event, app)
         } else {
-            handle_main_events(key_event, app).expect("Failed to handle main events");
+            handle_main_events(key_event, app)
+        };
+
+        // Failures end up in the log, booky keeps running
+        if let Err(e) = result {
+            app.log_error(e);
         }
     }
     Ok(())
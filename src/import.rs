@@ -0,0 +1,289 @@
+pub mod calibre;
+pub mod csv;
+pub mod goodreads;
+pub mod json;
+pub mod kindle;
+pub mod koreader;
+pub mod librarything;
+pub mod storygraph;
+
+use crate::authors::normalize_authors;
+use crate::database::models::NewBook;
+use crate::database::models::{Book, DeletedBook};
+use crate::database::Library;
+use crate::dates::DateOrder;
+use crate::error::BookyResult;
+use crate::form::{BookForm, FormField};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// What happened during an import.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Books that were added to the library.
+    pub created: usize,
+    /// Books that were already in the library.
+    pub duplicates: usize,
+    /// Books that are in the trash, by id and title. They are skipped like
+    /// duplicates, restoring them is up to the user.
+    pub in_trash: Vec<(i32, String)>,
+    /// Records that couldn't be imported, with where they are in the file and the reason.
+    pub rejected: Vec<(String, String)>,
+    /// Fields that were left out of an imported book, with where it is in the file and why.
+    pub warnings: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    /// Counts a book [`Duplicates::check`] found, or lists it when it is in the trash.
+    pub fn skip(&mut self, duplicates: &Duplicates, title: &str, author: &str) {
+        match duplicates.in_trash(title, author) {
+            // A second copy in the file is a duplicate of the first
+            Some(id) if !self.in_trash.iter().any(|(trashed, _)| *trashed == id) => {
+                add_in_trash(&mut self.in_trash, id, title);
+            }
+            _ => self.duplicates += 1,
+        }
+    }
+
+    /// Remembers that the record at `location`, like "Line 4", was rejected.
+    pub fn reject(&mut self, location: impl fmt::Display, reason: impl fmt::Display) {
+        self.rejected
+            .push((location.to_string(), reason.to_string()));
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (location, reason) in &self.rejected {
+            writeln!(f, "{}: {}", location, reason)?;
+        }
+        for (location, warning) in &self.warnings {
+            writeln!(f, "{}: {}, imported without it", location, warning)?;
+        }
+        write_in_trash(f, &self.in_trash)?;
+        write!(
+            f,
+            "Created {}, skipped {} duplicate(s) and {} book(s) in the trash, rejected {}",
+            self.created,
+            self.duplicates,
+            self.in_trash.len(),
+            self.rejected.len()
+        )
+    }
+}
+
+/// Remembers a book in the trash an import skipped, every book once.
+pub(crate) fn add_in_trash(in_trash: &mut Vec<(i32, String)>, id: i32, title: &str) {
+    if !in_trash.iter().any(|(trashed, _)| *trashed == id) {
+        in_trash.push((id, title.to_string()));
+    }
+}
+
+/// Lists the books an import skipped because they are in the trash.
+pub(crate) fn write_in_trash(
+    f: &mut fmt::Formatter<'_>,
+    in_trash: &[(i32, String)],
+) -> fmt::Result {
+    for (id, title) in in_trash {
+        writeln!(
+            f,
+            "In the trash: {}, skipped, `booky trash restore {}` brings it back",
+            title, id
+        )?;
+    }
+    Ok(())
+}
+
+/// Remembers which books are in the library so imports don't add them twice.
+///
+/// Two books are the same when their title and authors match, ignoring case
+/// and how the authors are written. Books in the trash count too, so an
+/// import doesn't add a second copy next to a deleted one.
+pub struct Duplicates {
+    seen: HashSet<(String, String)>,
+    trash: HashMap<(String, String), i32>,
+}
+
+impl Duplicates {
+    pub fn load(library: &mut Library) -> BookyResult<Self> {
+        let seen = library
+            .books()?
+            .iter()
+            .map(|book| Self::key(&book.title, &book.author))
+            .collect();
+        let trash = library
+            .trash()?
+            .iter()
+            .map(|book| (Self::key(&book.title, &book.author), book.id))
+            .collect();
+        Ok(Self { seen, trash })
+    }
+
+    /// The id of the book in the trash with this title and author.
+    pub fn in_trash(&self, title: &str, author: &str) -> Option<i32> {
+        self.trash.get(&Self::key(title, author)).copied()
+    }
+
+    /// Returns true if the book was seen before or is in the trash, otherwise remembers it.
+    pub fn check(&mut self, title: &str, author: &str) -> bool {
+        let key = Self::key(title, author);
+        self.trash.contains_key(&key) || !self.seen.insert(key)
+    }
+
+    pub fn check_book(&mut self, book: &Book) -> bool {
+        self.check(&book.title, &book.author)
+    }
+
+    // "Pratchett, Terry" is the same as "Terry Pratchett"
+    fn key(title: &str, author: &str) -> (String, String) {
+        (
+            title.trim().to_lowercase(),
+            normalize_authors(author).to_lowercase(),
+        )
+    }
+}
+
+/// Fields a [`Strictness::Lenient`] import leaves out when they don't validate.
+const OPTIONAL_FIELDS: [FormField; 6] = [
+    FormField::Rating,
+    FormField::SeriesIndex,
+    FormField::Isbn,
+    FormField::PublishedYear,
+    FormField::Pages,
+    FormField::Format,
+];
+
+/// How [`import_forms`] treats a book with an optional field that doesn't validate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Strictness {
+    /// The book is rejected, for files the user made.
+    Strict,
+    /// The field is left out with a warning, other applications export
+    /// values booky doesn't accept, like ISBNs with a wrong check digit.
+    Lenient,
+}
+
+/// Adds the valid forms to the library, this is the part all importers of
+/// foreign formats share.
+///
+/// Every form comes with its location in the file. Forms that couldn't be
+/// read or don't validate are rejected, see [`Strictness`] for the optional
+/// fields. Duplicates are skipped and nothing is saved on a dry run. It runs
+/// as a single batch, so a database error leaves the library untouched and
+/// undo takes back the whole import.
+pub(crate) fn import_forms<I>(
+    library: &mut Library,
+    dry_run: bool,
+    strictness: Strictness,
+    forms: I,
+) -> BookyResult<ImportReport>
+where
+    I: IntoIterator<Item = (String, BookyResult<BookForm>)>,
+{
+    library.batch("Import books", |library| {
+        let mut report = ImportReport::default();
+        let mut duplicates = Duplicates::load(library)?;
+
+        for (location, form) in forms {
+            let new_book = match form
+                .and_then(|form| validate(form, strictness, &location, &mut report.warnings))
+            {
+                Ok(new_book) => new_book,
+                Err(e) => {
+                    report.reject(location, e);
+                    continue;
+                }
+            };
+            if duplicates.check(&new_book.title, &new_book.author) {
+                report.skip(&duplicates, &new_book.title, &new_book.author);
+                continue;
+            }
+
+            if !dry_run {
+                library.create_book(&new_book)?;
+            }
+            report.created += 1;
+        }
+        Ok(report)
+    })
+}
+
+// Leaves out the optional fields that don't validate when that is all that is wrong
+fn validate(
+    mut form: BookForm,
+    strictness: Strictness,
+    location: &str,
+    warnings: &mut Vec<(String, String)>,
+) -> BookyResult<NewBook> {
+    // Importers rewrite the dates like 2023-04-12, the order doesn't matter
+    let errors = match form.validate(DateOrder::default()) {
+        Ok(new_book) => return Ok(new_book),
+        Err(errors) => errors,
+    };
+    let optional = errors
+        .iter()
+        .all(|(field, _)| OPTIONAL_FIELDS.contains(&field));
+    if strictness == Strictness::Strict || !optional {
+        return Err(errors.into());
+    }
+
+    for (field, message) in errors.iter() {
+        if let Some(value) = form.value_mut(field) {
+            value.clear();
+        }
+        warnings.push((location.to_string(), message.to_string()));
+    }
+    form.to_new_book(DateOrder::default())
+}
+
+/// Whether a book from another application with `title` and `author` is `book`.
+///
+/// Titles are compared without subtitles, series and punctuation, so
+/// "Leviathan Wakes (The Expanse Book 1)" is "Leviathan Wakes (The Expanse, #1)".
+/// Authors match when they share a name, which covers "Tolkien, J.R.R."
+/// and "J. R. R. Tolkien". A missing author matches any author.
+pub fn same_book(book: &Book, title: &str, author: &str) -> bool {
+    same_title_and_author(&book.title, &book.author, title, author)
+}
+
+/// Looks for a book in the trash that is the book with `title` and `author`, see [`same_book`].
+pub fn find_in_trash<'a>(
+    trash: &'a [DeletedBook],
+    title: &str,
+    author: &str,
+) -> Option<&'a DeletedBook> {
+    trash
+        .iter()
+        .find(|book| same_title_and_author(&book.title, &book.author, title, author))
+}
+
+fn same_title_and_author(book_title: &str, book_author: &str, title: &str, author: &str) -> bool {
+    if title_key(book_title) != title_key(title) {
+        return false;
+    }
+
+    let book_names = names(book_author);
+    let names = names(author);
+    book_names.is_empty() || names.is_empty() || names.iter().any(|name| book_names.contains(name))
+}
+
+fn title_key(title: &str) -> Vec<String> {
+    let title = title.split([':', '(']).next().unwrap_or_default();
+    words(title)
+}
+
+// Initials are too short to tell authors apart
+fn names(author: &str) -> Vec<String> {
+    words(author)
+        .into_iter()
+        .filter(|name| name.chars().count() > 2)
+        .collect()
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
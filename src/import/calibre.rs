@@ -0,0 +1,250 @@
+use super::{import_forms, ImportReport, Strictness};
+use crate::database::{self, Library};
+use crate::error::{BookyError, BookyResult};
+use crate::form::BookForm;
+use crate::series::format_index;
+use crate::status::ReadingStatus;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Bool, Double, Integer, Nullable, Text};
+use std::path::{Path, PathBuf};
+
+/// A book with everything booky needs joined in from Calibre's link tables.
+#[derive(Debug, QueryableByName)]
+struct CalibreBook {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    #[diesel(sql_type = Text)]
+    title: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    authors: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    tags: Option<String>,
+    /// Calibre stores half stars, so this goes from 0 to 10.
+    #[diesel(sql_type = Nullable<Integer>)]
+    rating: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    series: Option<String>,
+    #[diesel(sql_type = Double)]
+    series_index: f64,
+    #[diesel(sql_type = Bool)]
+    read: bool,
+    #[diesel(sql_type = Nullable<Text>)]
+    isbn: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    publisher: Option<String>,
+    #[diesel(sql_type = Nullable<Integer>)]
+    published_year: Option<i32>,
+    /// ISO 639 code like "eng".
+    #[diesel(sql_type = Nullable<Text>)]
+    language: Option<String>,
+    /// Whether Calibre has a file of the book.
+    #[diesel(sql_type = Bool)]
+    has_file: bool,
+}
+
+#[derive(Debug, QueryableByName)]
+struct CustomColumn {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+/// Imports the books of the Calibre library at `path`.
+///
+/// `path` is the library folder or its `metadata.db`, which is opened read
+/// only. Authors are joined with `&` like Calibre shows them, the tags,
+/// series, ISBN, publisher, year and language are kept and books with a
+/// file are ebooks. A book is read
+/// if it has a "read" tag or a yes/no column labelled "read", otherwise it
+/// is to-read.
+pub fn import(library: &mut Library, path: &Path, dry_run: bool) -> BookyResult<ImportReport> {
+    let mut calibre = open(path)?;
+    let books = sql_query(books_query(&mut calibre)?).load::<CalibreBook>(&mut calibre)?;
+
+    let forms = books
+        .into_iter()
+        .map(|book| (format!("Calibre book {}", book.id), Ok(to_form(book))));
+    import_forms(library, dry_run, Strictness::Lenient, forms)
+}
+
+fn open(path: &Path) -> BookyResult<SqliteConnection> {
+    let path: PathBuf = if path.is_dir() {
+        path.join("metadata.db")
+    } else {
+        path.to_path_buf()
+    };
+    if !path.is_file() {
+        return Err(BookyError::Import(format!(
+            "there is no Calibre library at {}",
+            path.display()
+        )));
+    }
+
+    database::establish_read_only_connection(&path)
+}
+
+// Calibre has no read status of its own, most people add a yes/no column for it
+fn books_query(calibre: &mut SqliteConnection) -> BookyResult<String> {
+    let read_column = sql_query(
+        "SELECT id FROM custom_columns WHERE label = 'read' AND datatype = 'bool' LIMIT 1",
+    )
+    .load::<CustomColumn>(calibre)?
+    .pop();
+    let read_by_column = match read_column {
+        Some(column) => format!(
+            "OR EXISTS (SELECT 1 FROM custom_column_{} c WHERE c.book = b.id AND c.value)",
+            column.id
+        ),
+        None => String::new(),
+    };
+
+    Ok(format!(
+        "SELECT b.id, b.title, b.series_index,
+            (SELECT group_concat(name, ' & ') FROM (
+                SELECT a.name FROM books_authors_link l JOIN authors a ON a.id = l.author
+                WHERE l.book = b.id ORDER BY l.id)) AS authors,
+            (SELECT group_concat(name, ', ') FROM (
+                SELECT t.name FROM books_tags_link l JOIN tags t ON t.id = l.tag
+                WHERE l.book = b.id AND lower(t.name) <> 'read' ORDER BY l.id)) AS tags,
+            (SELECT r.rating FROM books_ratings_link l JOIN ratings r ON r.id = l.rating
+                WHERE l.book = b.id) AS rating,
+            (SELECT s.name FROM books_series_link l JOIN series s ON s.id = l.series
+                WHERE l.book = b.id) AS series,
+            (SELECT i.val FROM identifiers i WHERE i.book = b.id AND i.type = 'isbn') AS isbn,
+            (SELECT p.name FROM books_publishers_link l JOIN publishers p ON p.id = l.publisher
+                WHERE l.book = b.id) AS publisher,
+            -- Calibre writes 0101-01-01 when the date is unknown
+            nullif(CAST(substr(b.pubdate, 1, 4) AS INTEGER), 101) AS published_year,
+            (SELECT g.lang_code FROM books_languages_link l JOIN languages g ON g.id = l.lang_code
+                WHERE l.book = b.id ORDER BY l.item_order LIMIT 1) AS language,
+            EXISTS (SELECT 1 FROM data d WHERE d.book = b.id) AS has_file,
+            (EXISTS (SELECT 1 FROM books_tags_link l JOIN tags t ON t.id = l.tag
+                WHERE l.book = b.id AND lower(t.name) = 'read') {}) AS read
+        FROM books b ORDER BY b.id",
+        read_by_column
+    ))
+}
+
+fn to_form(book: CalibreBook) -> BookForm {
+    // Calibre gives every book an index, even those without a series
+    let series_index = match book.series {
+        Some(_) => format_index(book.series_index),
+        None => String::new(),
+    };
+    let status = if book.read {
+        ReadingStatus::Finished
+    } else {
+        ReadingStatus::WantToRead
+    };
+
+    BookForm {
+        title: book.title,
+        author: book.authors.unwrap_or_default(),
+        tags: book.tags.unwrap_or_default(),
+        rating: book
+            .rating
+            .map(|rating| ((rating + 1) / 2).to_string())
+            .unwrap_or_default(),
+        status: status.to_string(),
+        series: book.series.unwrap_or_default(),
+        series_index,
+        isbn: book.isbn.unwrap_or_default(),
+        publisher: book.publisher.unwrap_or_default(),
+        published_year: book
+            .published_year
+            .map(|year| year.to_string())
+            .unwrap_or_default(),
+        pages: String::new(),
+        language: book.language.unwrap_or_default(),
+        format: if book.has_file { "ebook" } else { "" }.to_string(),
+        // Calibre only knows when a book was added, not when it was read
+        start_date: String::new(),
+        end_date: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+    use diesel::connection::SimpleConnection;
+
+    // Just the parts of Calibre's schema the import reads
+    const SCHEMA: &str = "
+        CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL,
+            series_index REAL NOT NULL DEFAULT 1.0, pubdate TIMESTAMP);
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL, sort TEXT);
+        CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+        CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER);
+        CREATE TABLE ratings (id INTEGER PRIMARY KEY, rating INTEGER);
+        CREATE TABLE books_ratings_link (id INTEGER PRIMARY KEY, book INTEGER, rating INTEGER);
+        CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER);
+        CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+        CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER, publisher INTEGER);
+        CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT NOT NULL);
+        CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER,
+            lang_code INTEGER, item_order INTEGER NOT NULL DEFAULT 0);
+        CREATE TABLE data (id INTEGER PRIMARY KEY, book INTEGER, format TEXT, name TEXT);
+        CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT, datatype TEXT);
+    ";
+
+    #[test]
+    fn books_come_from_a_calibre_library() {
+        let path = std::env::temp_dir().join(format!("booky-calibre-{}.db", std::process::id()));
+        let mut calibre = SqliteConnection::establish(&path.display().to_string()).unwrap();
+        calibre.batch_execute(SCHEMA).unwrap();
+        calibre
+            .batch_execute(
+                "INSERT INTO books VALUES (1, 'Dune', 1.0, '1965-08-01'),
+                    (2, 'Good Omens', 1.0, '0101-01-01');
+                INSERT INTO authors VALUES (1, 'Frank Herbert', 'Herbert, Frank'),
+                    (2, 'Terry Pratchett', 'Pratchett, Terry'), (3, 'Neil Gaiman', 'Gaiman, Neil');
+                INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 2, 2), (3, 2, 3);
+                INSERT INTO tags VALUES (1, 'sci-fi'), (2, 'Read');
+                INSERT INTO books_tags_link VALUES (1, 1, 1), (2, 1, 2);
+                INSERT INTO ratings VALUES (1, 9);
+                INSERT INTO books_ratings_link VALUES (1, 1, 1);
+                INSERT INTO series VALUES (1, 'Dune');
+                INSERT INTO books_series_link VALUES (1, 1, 1);
+                INSERT INTO identifiers VALUES (1, 1, 'isbn', '0441172717');
+                INSERT INTO publishers VALUES (1, 'Ace');
+                INSERT INTO books_publishers_link VALUES (1, 1, 1);
+                INSERT INTO languages VALUES (1, 'eng');
+                INSERT INTO books_languages_link VALUES (1, 1, 1, 0);
+                INSERT INTO data VALUES (1, 2, 'EPUB', 'Good Omens');",
+            )
+            .unwrap();
+        drop(calibre);
+
+        let mut library = memory_library();
+        let report = import(&mut library, &path, false);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.unwrap().created, 2);
+
+        let books = library.books().unwrap();
+        let dune = books.iter().find(|book| book.title == "Dune").unwrap();
+        assert_eq!(dune.author, "Frank Herbert");
+        assert_eq!(dune.tags, "sci-fi");
+        assert_eq!(dune.rating, 5);
+        assert_eq!(dune.status, ReadingStatus::Finished);
+        assert_eq!(dune.series.as_deref(), Some("Dune"));
+        assert_eq!(dune.series_index, Some(1.0));
+        assert_eq!(dune.isbn.as_deref(), Some("9780441172719"));
+        assert_eq!(dune.publisher.as_deref(), Some("Ace"));
+        assert_eq!(dune.published_year, Some(1965));
+        assert_eq!(dune.format, None);
+
+        let omens = books
+            .iter()
+            .find(|book| book.title == "Good Omens")
+            .unwrap();
+        assert_eq!(omens.author, "Terry Pratchett & Neil Gaiman");
+        assert_eq!(omens.status, ReadingStatus::WantToRead);
+        assert_eq!(omens.series, None);
+        assert_eq!(omens.published_year, None);
+        assert_eq!(omens.format.as_deref(), Some("ebook"));
+    }
+}
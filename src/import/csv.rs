@@ -0,0 +1,452 @@
+use super::{import_forms, ImportReport, Strictness};
+use crate::database::Library;
+use crate::dates::{DateOrder, DatePrecision, PartialDate};
+use crate::error::{BookyError, BookyResult};
+use crate::form::{format_date, parse_date, BookForm, DATE_FORMAT};
+use chrono::NaiveDate;
+use std::io::Read;
+use std::str::FromStr;
+
+/// A book field that a CSV column can be mapped onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Author,
+    Tags,
+    Rating,
+    Status,
+    Series,
+    SeriesIndex,
+    Isbn,
+    Publisher,
+    PublishedYear,
+    Pages,
+    Language,
+    Format,
+    StartDate,
+    EndDate,
+    /// How much of the start date is known, see [`DatePrecision`].
+    StartPrecision,
+    EndPrecision,
+}
+
+impl Field {
+    // The precisions have no field of their own, they change the dates
+    fn value_mut(self, form: &mut BookForm) -> Option<&mut String> {
+        let value = match self {
+            Field::Title => &mut form.title,
+            Field::Author => &mut form.author,
+            Field::Tags => &mut form.tags,
+            Field::Rating => &mut form.rating,
+            Field::Status => &mut form.status,
+            Field::Series => &mut form.series,
+            Field::SeriesIndex => &mut form.series_index,
+            Field::Isbn => &mut form.isbn,
+            Field::Publisher => &mut form.publisher,
+            Field::PublishedYear => &mut form.published_year,
+            Field::Pages => &mut form.pages,
+            Field::Language => &mut form.language,
+            Field::Format => &mut form.format,
+            Field::StartDate => &mut form.start_date,
+            Field::EndDate => &mut form.end_date,
+            Field::StartPrecision | Field::EndPrecision => return None,
+        };
+        Some(value)
+    }
+}
+
+impl FromStr for Field {
+    type Err = BookyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "title" => Ok(Field::Title),
+            "author" => Ok(Field::Author),
+            "tags" | "tag" | "genre" => Ok(Field::Tags),
+            "rating" => Ok(Field::Rating),
+            "status" => Ok(Field::Status),
+            "series" => Ok(Field::Series),
+            "series_index" | "volume" => Ok(Field::SeriesIndex),
+            "isbn" | "isbn13" | "isbn_13" | "isbn10" | "isbn_10" => Ok(Field::Isbn),
+            "publisher" => Ok(Field::Publisher),
+            "published_year" | "year" => Ok(Field::PublishedYear),
+            "pages" | "page_count" => Ok(Field::Pages),
+            "language" => Ok(Field::Language),
+            "format" => Ok(Field::Format),
+            "start_date" | "start" | "startdate" => Ok(Field::StartDate),
+            "end_date" | "end" | "enddate" => Ok(Field::EndDate),
+            "start_precision" => Ok(Field::StartPrecision),
+            "end_precision" => Ok(Field::EndPrecision),
+            _ => Err(BookyError::Validation(format!(
+                "Unknown field \"{}\", use title, author, tags, rating, status, series, series_index, isbn, publisher, published_year, pages, language, format, start_date, end_date, start_precision or end_precision",
+                s
+            ))),
+        }
+    }
+}
+
+/// Tells the importer which column holds which field.
+///
+/// Columns that are named after a field, like `title` or `start_date`,
+/// are mapped automatically.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMapping {
+    columns: Vec<(String, Field)>,
+}
+
+impl ColumnMapping {
+    /// Maps the column with the header `column` onto `field`.
+    pub fn map(&mut self, column: &str, field: Field) {
+        self.columns.push((column.trim().to_lowercase(), field));
+    }
+
+    /// Parses a `column=field` pair like `Date Read=end_date`.
+    pub fn map_pair(&mut self, pair: &str) -> BookyResult<()> {
+        let (column, field) = pair.rsplit_once('=').ok_or_else(|| {
+            BookyError::Validation(format!("Expected column=field, got \"{}\"", pair))
+        })?;
+        self.map(column, field.parse()?);
+        Ok(())
+    }
+
+    // Returns the field for every column of the header row
+    fn resolve(&self, headers: &csv::StringRecord) -> BookyResult<Vec<Option<Field>>> {
+        let fields: Vec<Option<Field>> = headers
+            .iter()
+            .map(|header| {
+                let header = header.trim().to_lowercase();
+                self.columns
+                    .iter()
+                    .find(|(column, _)| *column == header)
+                    .map(|(_, field)| *field)
+                    .or_else(|| header.parse().ok())
+            })
+            .collect();
+
+        if !fields.contains(&Some(Field::Title)) {
+            return Err(BookyError::Import(
+                "no column is mapped to title, use --map \"<column>=title\"".to_string(),
+            ));
+        }
+        Ok(fields)
+    }
+}
+
+/// Options for [`import`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub mapping: ColumnMapping,
+    pub delimiter: u8,
+    /// Check every row but don't save anything.
+    pub dry_run: bool,
+    /// How dates like `12/04/2023` are read.
+    pub date_order: DateOrder,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            mapping: ColumnMapping::default(),
+            delimiter: b',',
+            dry_run: false,
+            date_order: DateOrder::default(),
+        }
+    }
+}
+
+/// Imports a CSV file that has a header row.
+///
+/// Every row is validated like the add popup, rows that fail are reported
+/// with their line number and the other rows are still imported. The
+/// precision columns of booky's own export turn a date like `2019-01-01`
+/// back into `2019`.
+pub fn import(
+    library: &mut Library,
+    reader: impl Read,
+    options: &CsvOptions,
+) -> BookyResult<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .flexible(true)
+        .from_reader(reader);
+    let fields = options.mapping.resolve(reader.headers()?)?;
+
+    import_records(
+        library,
+        &mut reader,
+        options.dry_run,
+        Strictness::Strict,
+        |record| {
+            let mut form = BookForm::default();
+            let mut start_precision = "";
+            let mut end_precision = "";
+            for (field, value) in fields.iter().zip(record.iter()) {
+                match field {
+                    Some(Field::StartPrecision) => start_precision = value,
+                    Some(Field::EndPrecision) => end_precision = value,
+                    Some(field) => {
+                        if let Some(form_value) = field.value_mut(&mut form) {
+                            *form_value = value.trim().to_string();
+                        }
+                    }
+                    None => {}
+                }
+            }
+            // Read the dates in the configured order before the form sees them
+            let start = parse_date(&form.start_date, "Start date", options.date_order)?;
+            let end = parse_date(&form.end_date, "End date", options.date_order)?;
+            form.start_date = format_date(with_precision(start, start_precision)?);
+            form.end_date = format_date(with_precision(end, end_precision)?);
+            Ok(form)
+        },
+    )
+}
+
+// Only the known part of the date is kept, an empty precision keeps it as it is
+fn with_precision(date: Option<PartialDate>, precision: &str) -> BookyResult<Option<PartialDate>> {
+    if precision.trim().is_empty() {
+        return Ok(date);
+    }
+    let precision: DatePrecision = precision.parse()?;
+    Ok(date.map(|date| PartialDate::new(date.date, precision)))
+}
+
+/// Looks up the columns of a header row by name, ignoring case.
+pub(crate) struct Headers {
+    names: Vec<String>,
+}
+
+impl Headers {
+    pub fn new(headers: &csv::StringRecord) -> Self {
+        let names = headers
+            .iter()
+            .map(|header| header.trim().to_lowercase())
+            .collect();
+        Self { names }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.names.iter().position(|header| *header == name)
+    }
+
+    /// Like [`Headers::find`], but a missing column is an error.
+    pub fn require(&self, name: &str) -> BookyResult<usize> {
+        self.find(name)
+            .ok_or_else(|| BookyError::Import(format!("the file has no \"{}\" column", name)))
+    }
+}
+
+/// Returns the trimmed value of `column`, or an empty string if the row doesn't have it.
+pub(crate) fn value(record: &csv::StringRecord, column: Option<usize>) -> &str {
+    column
+        .and_then(|column| record.get(column))
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+/// Rewrites a date in `format` the way the form expects it, empty stays empty.
+pub(crate) fn convert_date(value: &str, column: &str, format: &str) -> BookyResult<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+
+    NaiveDate::parse_from_str(value, format)
+        .map(|date| date.format(DATE_FORMAT).to_string())
+        .map_err(|_| {
+            let expected = format
+                .replace("%Y", "year")
+                .replace("%m", "month")
+                .replace("%d", "day");
+            BookyError::Validation(format!(
+                "{} must look like {}, got \"{}\"",
+                column, expected, value
+            ))
+        })
+}
+
+/// Rounds a star rating like `3.75` to whole stars, empty stays empty.
+pub(crate) fn round_rating(value: &str, column: &str) -> BookyResult<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|rating| rating.is_finite())
+        .map(|rating| (rating.round() as i32).to_string())
+        .ok_or_else(|| {
+            BookyError::Validation(format!("{} must be a number, got \"{}\"", column, value))
+        })
+}
+
+/// Turns every record into a form with `to_form` and adds them with [`import_forms`].
+///
+/// Rows that can't be read are rejected with their line number like
+/// rows that don't validate.
+pub(crate) fn import_records<R, F>(
+    library: &mut Library,
+    reader: &mut csv::Reader<R>,
+    dry_run: bool,
+    strictness: Strictness,
+    mut to_form: F,
+) -> BookyResult<ImportReport>
+where
+    R: Read,
+    F: FnMut(&csv::StringRecord) -> BookyResult<BookForm>,
+{
+    let forms = reader.records().map(|record| match record {
+        Ok(record) => (location(record.position()), to_form(&record)),
+        Err(e) => (location(e.position()), Err(e.into())),
+    });
+    import_forms(library, dry_run, strictness, forms)
+}
+
+fn location(position: Option<&csv::Position>) -> String {
+    format!(
+        "Line {}",
+        position.map(csv::Position::line).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+
+    fn headers(names: &[&str]) -> csv::StringRecord {
+        csv::StringRecord::from(names.to_vec())
+    }
+
+    #[test]
+    fn columns_named_after_a_field_map_themselves() {
+        let fields = ColumnMapping::default()
+            .resolve(&headers(&[
+                "Title",
+                " author ",
+                "Start Date",
+                "Genre",
+                "Notes",
+            ]))
+            .unwrap();
+        assert_eq!(
+            fields,
+            [
+                Some(Field::Title),
+                Some(Field::Author),
+                Some(Field::StartDate),
+                Some(Field::Tags),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn mapped_columns_win_over_their_names() {
+        let mut mapping = ColumnMapping::default();
+        mapping.map_pair("Book Name=title").unwrap();
+        mapping.map_pair("Date = Read=end_date").unwrap();
+        mapping.map("Author", Field::Tags);
+        let fields = mapping
+            .resolve(&headers(&["book name", "Date = Read", "Author"]))
+            .unwrap();
+        assert_eq!(
+            fields,
+            [Some(Field::Title), Some(Field::EndDate), Some(Field::Tags)]
+        );
+
+        assert!(mapping.map_pair("title").is_err());
+        assert!(mapping.map_pair("Name=colour").is_err());
+        assert!(ColumnMapping::default()
+            .resolve(&headers(&["Name", "Author"]))
+            .is_err());
+    }
+
+    #[test]
+    fn rows_are_imported_through_the_mapping() {
+        let mut library = memory_library();
+        let mut mapping = ColumnMapping::default();
+        mapping.map("Name", Field::Title);
+        mapping.map("Finished", Field::EndDate);
+        let options = CsvOptions {
+            mapping,
+            delimiter: b';',
+            date_order: DateOrder::MonthDay,
+            ..CsvOptions::default()
+        };
+        let file = "Name;Author;Finished\nDune;Frank Herbert;12/04/2023\n;Nobody;\nMort;Terry Pratchett;\n";
+
+        let report = import(&mut library, file.as_bytes(), &options).unwrap();
+        assert_eq!(report.created, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, "Line 3");
+
+        let books = library.books().unwrap();
+        let dune = books.iter().find(|book| book.title == "Dune").unwrap();
+        assert_eq!(dune.author, "Frank Herbert");
+        assert_eq!(dune.end_date, NaiveDate::from_ymd_opt(2023, 12, 4));
+    }
+
+    #[test]
+    fn an_export_imports_as_it_was() {
+        let mut library = memory_library();
+        let forms = [
+            BookForm {
+                title: "Mort".to_string(),
+                author: "Terry Pratchett".to_string(),
+                tags: "fantasy, humor".to_string(),
+                rating: "4".to_string(),
+                series: "Discworld".to_string(),
+                series_index: "4".to_string(),
+                isbn: "0-441-17271-7".to_string(),
+                pages: "272".to_string(),
+                format: "paperback".to_string(),
+                start_date: "2019".to_string(),
+                end_date: "2019-06".to_string(),
+                ..BookForm::default()
+            },
+            BookForm {
+                title: "Dune, Part One".to_string(),
+                author: "Frank Herbert".to_string(),
+                start_date: "2024-01-03".to_string(),
+                ..BookForm::default()
+            },
+        ];
+        for form in &forms {
+            let new_book = form.to_new_book(DateOrder::default()).unwrap();
+            library.create_book(&new_book).unwrap();
+        }
+        let mut file = Vec::new();
+        crate::export::write_csv(library.books().unwrap(), &mut file).unwrap();
+
+        let mut imported = memory_library();
+        let report = import(&mut imported, file.as_slice(), &CsvOptions::default()).unwrap();
+        assert_eq!(report.created, 2);
+        assert_eq!(imported.books().unwrap(), library.books().unwrap());
+        let mort = &imported.books().unwrap()[0];
+        assert_eq!(mort.start_precision, DatePrecision::Year);
+        assert_eq!(mort.end_precision, DatePrecision::Month);
+    }
+
+    #[test]
+    fn books_in_the_trash_are_not_imported_again() {
+        let mut library = memory_library();
+        let form = BookForm {
+            title: "Dune".to_string(),
+            author: "Frank Herbert".to_string(),
+            ..BookForm::default()
+        };
+        let book = library
+            .create_book(&form.to_new_book(DateOrder::default()).unwrap())
+            .unwrap();
+        library.delete_book(book.id).unwrap();
+
+        let file = "title,author\ndune,\"Herbert, Frank\"\nDune,Frank Herbert\n";
+        let report = import(&mut library, file.as_bytes(), &CsvOptions::default()).unwrap();
+        assert_eq!(report.created, 0);
+        assert_eq!(report.in_trash, [(book.id, "dune".to_string())]);
+        assert_eq!(report.duplicates, 1);
+        assert!(library.books().unwrap().is_empty());
+    }
+}
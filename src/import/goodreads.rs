@@ -0,0 +1,197 @@
+use super::csv::{convert_date, import_records, value, Headers};
+use super::{ImportReport, Strictness};
+use crate::database::Library;
+use crate::error::BookyResult;
+use crate::form::BookForm;
+use crate::series::{format_index, split_series_title};
+use crate::status::ReadingStatus;
+use crate::tags::join_tags;
+use std::io::Read;
+
+/// Format of the dates in a Goodreads export.
+const GOODREADS_DATE_FORMAT: &str = "%Y/%m/%d";
+
+/// Shelves every Goodreads account has, they become the status of a book.
+const DEFAULT_SHELVES: [&str; 3] = ["read", "currently-reading", "to-read"];
+
+/// Imports `goodreads_library_export.csv`.
+///
+/// The exclusive shelf becomes the status, the custom shelves and exclusive
+/// shelves that aren't a status become tags
+/// and the series is split off titles like "Leviathan Wakes (The Expanse, #1)".
+/// The ISBN, publisher, year, page count and binding describe the edition.
+/// Goodreads doesn't export when a book was started, so only Date Read is
+/// kept, Date Added says nothing about reading it.
+pub fn import(
+    library: &mut Library,
+    reader: impl Read,
+    dry_run: bool,
+) -> BookyResult<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = Headers::new(reader.headers()?);
+    let title = Some(headers.require("Title")?);
+    let author = headers.find("Author");
+    let rating = headers.find("My Rating");
+    let shelf = headers.find("Exclusive Shelf");
+    let date_read = headers.find("Date Read");
+    let bookshelves = headers.find("Bookshelves");
+    let isbn = headers.find("ISBN");
+    let isbn13 = headers.find("ISBN13");
+    let publisher = headers.find("Publisher");
+    let year = headers.find("Year Published");
+    let pages = headers.find("Number of Pages");
+    let binding = headers.find("Binding");
+
+    import_records(
+        library,
+        &mut reader,
+        dry_run,
+        Strictness::Lenient,
+        |record| {
+            let shelf = value(record, shelf);
+            let status = ReadingStatus::parse(shelf);
+            let (title, series) = split_series_title(value(record, title));
+            let (series, series_index) = series.unzip();
+            Ok(BookForm {
+                title,
+                author: value(record, author).to_string(),
+                tags: custom_shelves(value(record, bookshelves), shelf, status.is_some()),
+                rating: value(record, rating).to_string(),
+                status: status.map(|status| status.to_string()).unwrap_or_default(),
+                series: series.unwrap_or_default(),
+                series_index: series_index.map(format_index).unwrap_or_default(),
+                isbn: match unquote_isbn(value(record, isbn13)) {
+                    "" => unquote_isbn(value(record, isbn)).to_string(),
+                    isbn13 => isbn13.to_string(),
+                },
+                publisher: value(record, publisher).to_string(),
+                published_year: value(record, year).to_string(),
+                pages: value(record, pages).to_string(),
+                language: String::new(),
+                format: format(value(record, binding)).to_string(),
+                start_date: String::new(),
+                end_date: convert_date(
+                    value(record, date_read),
+                    "Date Read",
+                    GOODREADS_DATE_FORMAT,
+                )?,
+            })
+        },
+    )
+}
+
+// Goodreads writes ISBNs as formulas like ="0441172717" so spreadsheets keep the zeros
+fn unquote_isbn(isbn: &str) -> &str {
+    isbn.trim_start_matches('=').trim_matches('"')
+}
+
+/// Turns a binding like "Mass Market Paperback" or "Kindle Edition" into a format.
+///
+/// Bindings that don't say how the book is read, like "Unknown Binding", are left out.
+fn format(binding: &str) -> &'static str {
+    let binding = binding.to_lowercase();
+    if binding.contains("audio") {
+        "audiobook"
+    } else if binding.contains("kindle") || binding.contains("ebook") {
+        "ebook"
+    } else if binding.contains("hardcover") || binding.contains("hardback") {
+        "hardcover"
+    } else if binding.contains("paperback") {
+        "paperback"
+    } else {
+        ""
+    }
+}
+
+// Bookshelves also lists the exclusive shelf, skip it when it is the status and skip the default ones
+fn custom_shelves(bookshelves: &str, exclusive_shelf: &str, is_status: bool) -> String {
+    let shelves: Vec<&str> = bookshelves
+        .split(',')
+        .map(str::trim)
+        .filter(|shelf| {
+            !shelf.is_empty()
+                && (!is_status || *shelf != exclusive_shelf)
+                && !DEFAULT_SHELVES.contains(shelf)
+        })
+        .collect();
+    join_tags(&shelves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+
+    const HEADER: &str = "Book Id,Title,Author,ISBN,ISBN13,My Rating,Publisher,Binding,Number of Pages,Year Published,Date Read,Date Added,Bookshelves,Exclusive Shelf\n";
+
+    #[test]
+    fn rows_become_books() {
+        let mut library = memory_library();
+        let file = format!(
+            "{}{}{}",
+            HEADER,
+            "1,\"Leviathan Wakes (The Expanse, #1)\",James S.A. Corey,\"=\"\"0441172717\"\"\",\"=\"\"\"\"\",4,Orbit,Kindle Edition,592,2011,2021/03/14,2020/01/01,\"space, read, favorites\",read\n",
+            "2,Dune,Frank Herbert,,\"=\"\"9780441172719\"\"\",0,Ace,Unknown Binding,,,,2020/01/01,\"paper-pile, to-read\",paper-pile\n"
+        );
+        let report = import(&mut library, file.as_bytes(), false).unwrap();
+        assert_eq!(report.created, 2);
+        assert!(report.warnings.is_empty());
+
+        let books = library.books().unwrap();
+        let leviathan = books
+            .iter()
+            .find(|book| book.author == "James S.A. Corey")
+            .unwrap();
+        assert_eq!(leviathan.title, "Leviathan Wakes");
+        assert_eq!(leviathan.series.as_deref(), Some("The Expanse"));
+        assert_eq!(leviathan.series_index, Some(1.0));
+        assert_eq!(leviathan.isbn.as_deref(), Some("9780441172719"));
+        assert_eq!(leviathan.status, ReadingStatus::Finished);
+        assert_eq!(leviathan.tags, "favorites, space");
+        assert_eq!(leviathan.format.as_deref(), Some("ebook"));
+        assert_eq!(leviathan.start_date, None);
+        assert_eq!(
+            leviathan.end_date,
+            chrono::NaiveDate::from_ymd_opt(2021, 3, 14)
+        );
+
+        let dune = books.iter().find(|book| book.title == "Dune").unwrap();
+        assert_eq!(dune.isbn.as_deref(), Some("9780441172719"));
+        assert_eq!(dune.status, ReadingStatus::WantToRead);
+        assert_eq!(dune.tags, "paper-pile");
+        assert_eq!(dune.rating, 0);
+        assert_eq!(dune.format, None);
+    }
+
+    #[test]
+    fn a_bad_optional_field_is_left_out() {
+        let mut library = memory_library();
+        let file = format!(
+            "{}{}",
+            HEADER,
+            "1,Dune,Frank Herbert,\"=\"\"0441172718\"\"\",,5,Ace,Paperback,-1,1990,,2020/01/01,,read\n"
+        );
+        let report = import(&mut library, file.as_bytes(), false).unwrap();
+        assert_eq!(report.created, 1);
+        assert_eq!(
+            report.warnings,
+            [
+                (
+                    "Line 2".to_string(),
+                    "\"0441172718\" is not a valid ISBN, check for typos".to_string()
+                ),
+                (
+                    "Line 2".to_string(),
+                    "Pages must be more than 0".to_string()
+                ),
+            ]
+        );
+
+        let book = &library.books().unwrap()[0];
+        assert_eq!(book.title, "Dune");
+        assert_eq!(book.rating, 5);
+        assert_eq!(book.isbn, None);
+        assert_eq!(book.pages, None);
+        assert_eq!(book.format.as_deref(), Some("paperback"));
+    }
+}
@@ -0,0 +1,158 @@
+use super::{Duplicates, ImportReport};
+use crate::database::models::{NewBook, NewQuote, NewReadingSession, ReadingDay};
+use crate::database::Library;
+use crate::error::{BookyError, BookyResult};
+use crate::export::{LibraryExport, EXPORT_VERSION};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+/// Imports a booky JSON export, see [`crate::export::write_json`].
+///
+/// Books keep their id unless another book already uses it,
+/// books with the same title and author as an existing one are skipped.
+/// Version 1 exports are still understood, the dates of every book become
+/// its only reading session. Sessions, quotes and the reading log go to
+/// their book.
+pub fn import(library: &mut Library, reader: impl Read) -> BookyResult<ImportReport> {
+    let export: LibraryExport = serde_json::from_reader(reader)?;
+    if export.version > EXPORT_VERSION {
+        return Err(BookyError::Import(format!(
+            "this export has version {} but booky only understands up to version {}, update booky first",
+            export.version, EXPORT_VERSION
+        )));
+    }
+
+    library.batch("Import a booky export", |library| {
+        let mut report = ImportReport::default();
+        let mut duplicates = Duplicates::load(library)?;
+        let mut book_ids = HashMap::new();
+        // Books in the trash still hold on to their id
+        let trashed: HashSet<i32> = library.trash()?.into_iter().map(|book| book.id).collect();
+
+        for book in &export.books {
+            if duplicates.check_book(book) {
+                report.skip(&duplicates, &book.title, &book.author);
+                continue;
+            }
+
+            let book_id = match library.book(book.id)? {
+                Some(_) => None,
+                None if trashed.contains(&book.id) => None,
+                None => Some(book.id),
+            };
+            let created = library.insert_book(book_id, &NewBook::from(book))?;
+            book_ids.insert(book.id, created.id);
+            report.created += 1;
+
+            // Version 1 has no sessions, only the dates of the latest one
+            if export.version < 2 {
+                if let Some(session) =
+                    NewReadingSession::from_dates(created.id, &NewBook::from(book))
+                {
+                    library.create_session(&session)?;
+                }
+            }
+        }
+
+        for session in &export.sessions {
+            // Sessions of skipped duplicates are skipped as well
+            if let Some(book_id) = book_ids.get(&session.book_id) {
+                library.create_session(&NewReadingSession {
+                    book_id: *book_id,
+                    ..NewReadingSession::from(session)
+                })?;
+            }
+        }
+
+        for quote in &export.quotes {
+            if let Some(book_id) = book_ids.get(&quote.book_id) {
+                library.create_quote(&NewQuote {
+                    book_id: *book_id,
+                    text: quote.text.clone(),
+                    location: quote.location.clone(),
+                    added_at: quote.added_at,
+                })?;
+            }
+        }
+
+        for reading_day in &export.reading_log {
+            if let Some(book_id) = book_ids.get(&reading_day.book_id) {
+                library.log_reading(&ReadingDay {
+                    book_id: *book_id,
+                    ..reading_day.clone()
+                })?;
+            }
+        }
+        Ok(report)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::memory_library;
+    use crate::database::models::{NewQuote, ReadingDay};
+    use crate::dates::DateOrder;
+    use crate::export;
+    use crate::form::BookForm;
+
+    #[test]
+    fn an_export_imports_with_its_quotes_and_reading_log() {
+        let mut library = memory_library();
+        let form = BookForm {
+            title: "Dune".to_string(),
+            author: "Frank Herbert".to_string(),
+            start_date: "2024-01".to_string(),
+            ..BookForm::default()
+        };
+        let book = library
+            .create_book(&form.to_new_book(DateOrder::default()).unwrap())
+            .unwrap();
+        library
+            .create_quote(&NewQuote {
+                book_id: book.id,
+                text: "Fear is the mind-killer.".to_string(),
+                location: Some("page 12".to_string()),
+                added_at: None,
+            })
+            .unwrap();
+        library
+            .log_reading(&ReadingDay {
+                book_id: book.id,
+                day: chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+                seconds: 1800,
+                pages: 20,
+            })
+            .unwrap();
+        // Books in the trash are left out with everything that belongs to them
+        let trashed = library
+            .create_book(&form.to_new_book(DateOrder::default()).unwrap())
+            .unwrap();
+        library.delete_book(trashed.id).unwrap();
+
+        let mut json = Vec::new();
+        export::write_json(
+            library.books().unwrap(),
+            library.all_sessions().unwrap(),
+            library.all_quotes().unwrap(),
+            library.full_reading_log().unwrap(),
+            &mut json,
+        )
+        .unwrap();
+        let mut copy = memory_library();
+        super::import(&mut copy, json.as_slice()).unwrap();
+
+        assert_eq!(
+            copy.quotes(book.id).unwrap(),
+            library.quotes(book.id).unwrap()
+        );
+        assert_eq!(
+            copy.sessions(book.id).unwrap(),
+            library.sessions(book.id).unwrap()
+        );
+        assert_eq!(
+            copy.reading_log(book.id).unwrap(),
+            library.reading_log(book.id).unwrap()
+        );
+        assert_eq!(copy.all_sessions().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,423 @@
+use super::{add_in_trash, find_in_trash, same_book, write_in_trash};
+use crate::database::models::{Book, NewQuote, Quote};
+use crate::database::Library;
+use crate::dates::DateOrder;
+use crate::error::{BookyError, BookyResult};
+use crate::form::{BookForm, DATE_FORMAT};
+use crate::status::ReadingStatus;
+use chrono::NaiveDateTime;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+/// Kindle puts this line between two clippings.
+const SEPARATOR: &str = "==========";
+
+/// Formats of the "Added on" timestamp, US and UK Kindles write it differently.
+const ADDED_FORMATS: [&str; 2] = ["%A, %B %d, %Y %I:%M:%S %p", "%A, %d %B %Y %H:%M:%S"];
+
+/// What happened during a clippings import.
+#[derive(Debug, Default)]
+pub struct ClippingsReport {
+    /// Highlights that were added as quotes.
+    pub quotes: usize,
+    /// Quotes that were replaced by a longer highlight of the same passage.
+    pub extended: usize,
+    /// Highlights that were already saved.
+    pub duplicates: usize,
+    /// Books that weren't in the library yet.
+    pub books_created: usize,
+    /// Notes and bookmarks, they have no highlighted text.
+    pub ignored: usize,
+    /// Books in the trash by id and title, their highlights were skipped.
+    pub in_trash: Vec<(i32, String)>,
+    /// Clippings that couldn't be imported, with their number and the reason.
+    pub rejected: Vec<(String, String)>,
+}
+
+impl ClippingsReport {
+    fn reject(&mut self, location: impl fmt::Display, reason: impl fmt::Display) {
+        self.rejected
+            .push((location.to_string(), reason.to_string()));
+    }
+}
+
+impl fmt::Display for ClippingsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (location, reason) in &self.rejected {
+            writeln!(f, "{}: {}", location, reason)?;
+        }
+        write_in_trash(f, &self.in_trash)?;
+        write!(
+            f,
+            "Added {} quote(s), extended {}, skipped {} duplicate(s), created {} book(s), ignored {} note(s) and bookmark(s), rejected {}",
+            self.quotes,
+            self.extended,
+            self.duplicates,
+            self.books_created,
+            self.ignored,
+            self.rejected.len()
+        )
+    }
+}
+
+/// A highlight from `My Clippings.txt`.
+#[derive(Debug)]
+struct Clipping {
+    title: String,
+    author: String,
+    location: Option<String>,
+    added_at: Option<NaiveDateTime>,
+    text: String,
+}
+
+/// Imports the highlights from Kindle's `My Clippings.txt` as quotes.
+///
+/// A highlight belongs to the book with a similar title and author, see
+/// [`super::same_book`], or to a new book when there is none. Highlights that are
+/// already saved are skipped, and when a passage was highlighted again with
+/// more text the longer highlight replaces the old quote. The highlights of
+/// a book in the trash are skipped, no second copy of the book is made.
+pub fn import(
+    library: &mut Library,
+    mut reader: impl Read,
+    dry_run: bool,
+) -> BookyResult<ClippingsReport> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+
+    library.batch("Import Kindle clippings", |library| {
+        let mut report = ClippingsReport::default();
+        let mut books = library.books()?;
+        let trash = library.trash()?;
+        let mut quotes: HashMap<i32, Vec<Quote>> = HashMap::new();
+
+        for (index, entry) in content.split(SEPARATOR).enumerate() {
+            if entry.trim().is_empty() {
+                continue;
+            }
+            let location = format!("Clipping {}", index + 1);
+            let clipping = match parse_clipping(entry) {
+                Ok(Some(clipping)) => clipping,
+                Ok(None) => {
+                    report.ignored += 1;
+                    continue;
+                }
+                Err(e) => {
+                    report.reject(location, e);
+                    continue;
+                }
+            };
+
+            let book_id = match books
+                .iter()
+                .find(|book| same_book(book, &clipping.title, &clipping.author))
+            {
+                Some(book) => book.id,
+                None => {
+                    if let Some(book) = find_in_trash(&trash, &clipping.title, &clipping.author) {
+                        add_in_trash(&mut report.in_trash, book.id, &book.title);
+                        continue;
+                    }
+                    let book = match create_book(library, &clipping, dry_run, books.len()) {
+                        Ok(book) => book,
+                        Err(e) => {
+                            report.reject(location, e);
+                            continue;
+                        }
+                    };
+                    report.books_created += 1;
+                    let id = book.id;
+                    books.push(book);
+                    id
+                }
+            };
+
+            let book_quotes = match quotes.entry(book_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                // Books made up during a dry run have no quotes yet
+                Entry::Vacant(entry) if book_id < 0 => entry.insert(Vec::new()),
+                Entry::Vacant(entry) => entry.insert(library.quotes(book_id)?),
+            };
+            let new_quote = NewQuote {
+                book_id,
+                text: clipping.text,
+                location: clipping.location,
+                added_at: clipping.added_at,
+            };
+
+            if book_quotes
+                .iter()
+                .any(|quote| quote.text.contains(&new_quote.text))
+            {
+                report.duplicates += 1;
+            } else if let Some(quote) = book_quotes
+                .iter_mut()
+                .find(|quote| new_quote.text.contains(&quote.text))
+            {
+                if dry_run {
+                    quote.text = new_quote.text;
+                } else {
+                    *quote = library.update_quote(quote.id, &new_quote)?;
+                }
+                report.extended += 1;
+            } else {
+                let quote = if dry_run {
+                    Quote {
+                        id: 0,
+                        book_id,
+                        text: new_quote.text,
+                        location: new_quote.location,
+                        added_at: new_quote.added_at,
+                    }
+                } else {
+                    library.create_quote(&new_quote)?
+                };
+                book_quotes.push(quote);
+                report.quotes += 1;
+            }
+        }
+        Ok(report)
+    })
+}
+
+// Books created during a dry run get a negative id so they can still be matched
+fn create_book(
+    library: &mut Library,
+    clipping: &Clipping,
+    dry_run: bool,
+    book_count: usize,
+) -> BookyResult<Book> {
+    let form = BookForm {
+        title: clipping.title.clone(),
+        author: clipping.author.clone(),
+        status: ReadingStatus::Reading.to_string(),
+        start_date: clipping
+            .added_at
+            .map(|added_at| added_at.format(DATE_FORMAT).to_string())
+            .unwrap_or_default(),
+        ..BookForm::default()
+    };
+    let new_book = form.to_new_book(DateOrder::default())?;
+
+    if dry_run {
+        return Ok(Book {
+            id: -(book_count as i32) - 1,
+            title: new_book.title,
+            author: new_book.author,
+            tags: new_book.tags,
+            rating: new_book.rating,
+            status: new_book.status,
+            series: new_book.series,
+            series_index: new_book.series_index,
+            isbn: new_book.isbn,
+            publisher: new_book.publisher,
+            published_year: new_book.published_year,
+            pages: new_book.pages,
+            language: new_book.language,
+            format: new_book.format,
+            start_date: new_book.start_date,
+            end_date: new_book.end_date,
+            start_precision: new_book.start_precision,
+            end_precision: new_book.end_precision,
+            times_read: 0,
+        });
+    }
+    library.create_book(&new_book)
+}
+
+/// Parses a single clipping, notes and bookmarks are skipped with `None`.
+///
+/// A clipping looks like this:
+///
+/// ```text
+/// The Hobbit (Tolkien, J.R.R.)
+/// - Your Highlight on page 12 | Location 150-152 | Added on Sunday, 5 March 2023 14:02:11
+///
+/// In a hole in the ground there lived a hobbit.
+/// ```
+fn parse_clipping(entry: &str) -> BookyResult<Option<Clipping>> {
+    let mut lines = entry
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .skip_while(|line| line.is_empty());
+    let title_line = lines.next().unwrap_or_default();
+    let meta = lines
+        .next()
+        .ok_or_else(|| BookyError::Validation("The clipping has no details line".to_string()))?;
+
+    let kind = meta.to_lowercase();
+    if kind.contains("note") || kind.contains("bookmark") {
+        return Ok(None);
+    }
+    if !kind.contains("highlight") {
+        return Err(BookyError::Validation(format!(
+            "Unknown clipping \"{}\", only highlights, notes and bookmarks are known",
+            meta
+        )));
+    }
+
+    let text = lines
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    if text.is_empty() {
+        return Err(BookyError::Validation(
+            "The highlight is empty, Kindle leaves out the text when a book's clipping limit is reached"
+                .to_string(),
+        ));
+    }
+
+    let (title, author) = split_title_line(title_line);
+    let (location, added_at) = parse_meta(meta);
+    Ok(Some(Clipping {
+        title,
+        author,
+        location,
+        added_at,
+        text,
+    }))
+}
+
+// The author is in the last parentheses, the title can have some too
+fn split_title_line(line: &str) -> (String, String) {
+    match line
+        .strip_suffix(')')
+        .and_then(|line| line.rsplit_once('('))
+    {
+        Some((title, authors)) => (title.trim().to_string(), author_name(authors)),
+        None => (line.to_string(), String::new()),
+    }
+}
+
+// Kindle writes "Tolkien, J.R.R.; Anderson, Douglas", booky "J.R.R. Tolkien & Douglas Anderson"
+fn author_name(authors: &str) -> String {
+    authors
+        .split(';')
+        .map(|author| match author.split_once(',') {
+            Some((last, first)) if !first.contains(',') => {
+                format!("{} {}", first.trim(), last.trim())
+            }
+            _ => author.trim().to_string(),
+        })
+        .filter(|author| !author.is_empty())
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+// Returns everything between the kind and the timestamp, and the timestamp
+fn parse_meta(meta: &str) -> (Option<String>, Option<NaiveDateTime>) {
+    let mut location = Vec::new();
+    let mut added_at = None;
+
+    for (index, part) in meta.trim_start_matches('-').split('|').enumerate() {
+        let part = part.trim();
+        if let Some(added) = part.strip_prefix("Added on") {
+            added_at = ADDED_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(added.trim(), format).ok());
+            continue;
+        }
+
+        // "Your Highlight on page 12" becomes "page 12"
+        let part = match index {
+            0 => part
+                .find("ighlight")
+                .map(|start| part[start + "ighlight".len()..].trim())
+                .map(|rest| {
+                    rest.strip_prefix("on ")
+                        .or_else(|| rest.strip_prefix("at "))
+                        .unwrap_or(rest)
+                })
+                .unwrap_or(part),
+            _ => part,
+        };
+        if !part.is_empty() {
+            location.push(part);
+        }
+    }
+
+    let location = Some(location.join(" | ")).filter(|location| !location.is_empty());
+    (location, added_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_keep_their_book_location_and_text() {
+        let clipping = parse_clipping(
+            "\u{feff}The Hobbit (There and Back Again) (Tolkien, J.R.R.; Anderson, Douglas)\n\
+             - Your Highlight on page 12 | Location 150-152 | Added on Sunday, 5 March 2023 14:02:11\n\
+             \n\
+             In a hole in the ground\n\
+             there lived a hobbit.\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(clipping.title, "The Hobbit (There and Back Again)");
+        assert_eq!(clipping.author, "J.R.R. Tolkien & Douglas Anderson");
+        assert_eq!(
+            clipping.location.as_deref(),
+            Some("page 12 | Location 150-152")
+        );
+        assert_eq!(
+            clipping.added_at.map(|added_at| added_at.to_string()),
+            Some("2023-03-05 14:02:11".to_string())
+        );
+        assert_eq!(
+            clipping.text,
+            "In a hole in the ground\nthere lived a hobbit."
+        );
+    }
+
+    #[test]
+    fn us_kindles_write_the_month_first() {
+        let (location, added_at) = parse_meta(
+            "- Your Highlight at location 10-12 | Added on Sunday, March 5, 2023 2:02:11 PM",
+        );
+        assert_eq!(location.as_deref(), Some("location 10-12"));
+        assert_eq!(
+            added_at.map(|added_at| added_at.to_string()),
+            Some("2023-03-05 14:02:11".to_string())
+        );
+    }
+
+    #[test]
+    fn notes_and_bookmarks_are_skipped() {
+        let note = "Dune (Frank Herbert)\n- Your Note on page 3 | Added on Monday, 6 March 2023 09:00:00\n\nRemember this\n";
+        let bookmark = "Dune (Frank Herbert)\n- Your Bookmark on page 3 | Added on Monday, 6 March 2023 09:00:00\n\n\n";
+        assert!(parse_clipping(note).unwrap().is_none());
+        assert!(parse_clipping(bookmark).unwrap().is_none());
+    }
+
+    #[test]
+    fn broken_clippings_are_errors() {
+        assert!(parse_clipping("Dune (Frank Herbert)\n").is_err());
+        assert!(parse_clipping("Dune (Frank Herbert)\n- Your Clip on page 3\n\nText\n").is_err());
+        // Kindle leaves out the text once the clipping limit is reached
+        assert!(parse_clipping("Dune (Frank Herbert)\n- Your Highlight on page 3\n\n\n").is_err());
+    }
+
+    #[test]
+    fn highlights_of_books_in_the_trash_are_skipped() {
+        let mut library = crate::database::memory_library();
+        let clipping = "Dune (Frank Herbert)\n\
+            - Your Highlight on page 12 | Added on Sunday, 5 March 2023 14:02:11\n\
+            \n\
+            Fear is the mind-killer.\n\
+            ==========\n";
+        import(&mut library, clipping.as_bytes(), false).unwrap();
+        let book = library.books().unwrap()[0].clone();
+        library.delete_book(book.id).unwrap();
+
+        let report = import(&mut library, clipping.as_bytes(), false).unwrap();
+        assert_eq!(report.in_trash, [(book.id, "Dune".to_string())]);
+        assert_eq!(report.books_created, 0);
+        assert!(library.books().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,521 @@
+use super::{add_in_trash, find_in_trash, same_book, write_in_trash};
+use crate::database::models::{Book, DeletedBook, NewBook, NewReadingSession, ReadingDay};
+use crate::database::{self, Library};
+use crate::dates::DatePrecision;
+use crate::error::{BookyError, BookyResult};
+use crate::status::ReadingStatus;
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Date, Integer, Text};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the database KOReader keeps its reading statistics in.
+const STATISTICS_FILE: &str = "statistics.sqlite3";
+
+/// What happened during a KOReader import.
+#[derive(Debug, Default)]
+pub struct KoreaderReport {
+    /// Books whose dates or status changed.
+    pub books_updated: usize,
+    /// Days of reading time that were saved.
+    pub days: usize,
+    /// KOReader books that aren't in the library, by title.
+    pub unmatched: Vec<String>,
+    /// KOReader books that are in the trash by id and title, they are left as they are.
+    pub in_trash: Vec<(i32, String)>,
+}
+
+impl fmt::Display for KoreaderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for title in &self.unmatched {
+            writeln!(f, "Not in the library: {}", title)?;
+        }
+        write_in_trash(f, &self.in_trash)?;
+        write!(
+            f,
+            "Updated {} book(s), logged {} day(s) of reading, {} book(s) not found, {} in the trash",
+            self.books_updated,
+            self.days,
+            self.unmatched.len(),
+            self.in_trash.len()
+        )
+    }
+}
+
+/// Reading time of a KOReader book on a single day.
+#[derive(Debug, QueryableByName)]
+struct StatisticsDay {
+    #[diesel(sql_type = Text)]
+    title: String,
+    #[diesel(sql_type = Text)]
+    authors: String,
+    #[diesel(sql_type = Date)]
+    day: NaiveDate,
+    #[diesel(sql_type = Integer)]
+    seconds: i32,
+    #[diesel(sql_type = Integer)]
+    pages: i32,
+}
+
+/// The parts of a `metadata.*.lua` sidecar booky cares about.
+#[derive(Debug)]
+struct Sidecar {
+    title: String,
+    authors: String,
+    /// "reading", "complete" or "abandoned", which KOReader shows as on hold.
+    status: Option<String>,
+    /// The day the status was last changed.
+    modified: Option<NaiveDate>,
+}
+
+/// Everything KOReader knows about a book in the library.
+#[derive(Debug, Default)]
+struct Activity {
+    days: BTreeMap<NaiveDate, (i32, i32)>,
+    status: Option<String>,
+    modified: Option<NaiveDate>,
+}
+
+/// Imports reading time and progress from KOReader.
+///
+/// Every path is a `statistics.sqlite3`, a `metadata.*.lua` sidecar or a
+/// folder that is searched for both, like the root of an e-reader. Only
+/// books that are already in the library are updated, they are matched by
+/// title and author like [`super::same_book`]. Books in the trash are
+/// reported and left alone.
+///
+/// The first day a book was read becomes its start date. A book KOReader
+/// marks as complete is read and ends on the last day it was read. When
+/// the first day is after the book was finished it is being read again, so
+/// a new session is started and the earlier one is kept as it was.
+pub fn import(
+    library: &mut Library,
+    paths: &[PathBuf],
+    dry_run: bool,
+) -> BookyResult<KoreaderReport> {
+    let mut statistics = Vec::new();
+    let mut sidecars = Vec::new();
+    for path in paths {
+        if !path.exists() {
+            return Err(BookyError::Import(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+        find_files(path, &mut statistics, &mut sidecars)?;
+    }
+    if statistics.is_empty() && sidecars.is_empty() {
+        return Err(BookyError::Import(
+            "no statistics.sqlite3 or metadata.*.lua sidecar was found".to_string(),
+        ));
+    }
+
+    library.batch("Import from KOReader", |library| {
+        let mut report = KoreaderReport::default();
+        let books = library.books()?;
+        let trash = library.trash()?;
+        let mut activity: HashMap<i32, Activity> = HashMap::new();
+
+        for path in &statistics {
+            for day in read_statistics(path)? {
+                let Some(book) = find_book(&books, &trash, &day.title, &day.authors, &mut report)
+                else {
+                    continue;
+                };
+                // Two editions of the same book add up
+                let (seconds, pages) = activity
+                    .entry(book.id)
+                    .or_default()
+                    .days
+                    .entry(day.day)
+                    .or_default();
+                *seconds += day.seconds;
+                *pages += day.pages;
+            }
+        }
+        for path in &sidecars {
+            let sidecar = read_sidecar(path)?;
+            let Some(book) = find_book(
+                &books,
+                &trash,
+                &sidecar.title,
+                &sidecar.authors,
+                &mut report,
+            ) else {
+                continue;
+            };
+            let activity = activity.entry(book.id).or_default();
+            activity.status = sidecar.status;
+            activity.modified = sidecar.modified;
+        }
+
+        for book in &books {
+            let Some(activity) = activity.get(&book.id) else {
+                continue;
+            };
+
+            let (updated, reread) = apply(book, activity);
+            if updated != NewBook::from(book) {
+                if !dry_run {
+                    if reread {
+                        library.create_session(&NewReadingSession {
+                            book_id: book.id,
+                            started: updated.start_date,
+                            finished: None,
+                            outcome: None,
+                            pages: None,
+                            minutes: None,
+                            started_precision: DatePrecision::Day,
+                            finished_precision: DatePrecision::Day,
+                        })?;
+                    }
+                    // Sets the dates of the latest session, which is the new one for a reread
+                    library.update_book(book.id, &updated)?;
+                }
+                report.books_updated += 1;
+            }
+
+            for (day, (seconds, pages)) in &activity.days {
+                if !dry_run {
+                    library.log_reading(&ReadingDay {
+                        book_id: book.id,
+                        day: *day,
+                        seconds: *seconds,
+                        pages: *pages,
+                    })?;
+                }
+                report.days += 1;
+            }
+        }
+        Ok(report)
+    })
+}
+
+fn find_book<'a>(
+    books: &'a [Book],
+    trash: &[DeletedBook],
+    title: &str,
+    authors: &str,
+    report: &mut KoreaderReport,
+) -> Option<&'a Book> {
+    // KOReader puts every author on its own line
+    let authors = authors.replace('\n', " & ");
+    let book = books.iter().find(|book| same_book(book, title, &authors));
+    if book.is_some() {
+        return book;
+    }
+    match find_in_trash(trash, title, &authors) {
+        Some(trashed) => add_in_trash(&mut report.in_trash, trashed.id, &trashed.title),
+        None if !report.unmatched.iter().any(|unmatched| unmatched == title) => {
+            report.unmatched.push(title.to_string());
+        }
+        None => {}
+    }
+    None
+}
+
+// Returns the book with the dates and status KOReader knows about and
+// whether it is being read again, a reread gets a session of its own
+fn apply(book: &Book, activity: &Activity) -> (NewBook, bool) {
+    let mut updated = NewBook::from(book);
+    let first_day = activity.days.keys().next().copied();
+    let last_day = activity.days.keys().next_back().copied();
+    // Starting after the last read ended, the old dates stay with that read
+    let reread = matches!(
+        (first_day, book.end()),
+        (Some(first_day), Some(end)) if first_day > end.last_day()
+    );
+
+    if first_day.is_some() {
+        updated.start_date = first_day;
+        updated.start_precision = DatePrecision::Day;
+    }
+    if reread {
+        updated.end_date = None;
+        updated.end_precision = DatePrecision::Day;
+    }
+    match activity.status.as_deref() {
+        Some("complete") => {
+            updated.status = ReadingStatus::Finished;
+            if let Some(end_date) = last_day.or(activity.modified) {
+                updated.end_date = Some(end_date);
+                updated.end_precision = DatePrecision::Day;
+            }
+        }
+        // KOReader's abandoned means on hold, the book can still be finished
+        Some("abandoned") => updated.status = ReadingStatus::Paused,
+        Some("reading") => updated.status = ReadingStatus::Reading,
+        // Without a sidecar all we know is that the book was opened
+        _ if first_day.is_some() && (reread || book.status != ReadingStatus::Finished) => {
+            updated.status = ReadingStatus::Reading;
+        }
+        _ => {}
+    }
+    (updated, reread)
+}
+
+// Looks for KOReader files in `path` and everything below it
+fn find_files(
+    path: &Path,
+    statistics: &mut Vec<PathBuf>,
+    sidecars: &mut Vec<PathBuf>,
+) -> BookyResult<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            find_files(&entry?.path(), statistics, sidecars)?;
+        }
+        return Ok(());
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    if name == STATISTICS_FILE {
+        statistics.push(path.to_path_buf());
+    } else if name.starts_with("metadata.") && name.ends_with(".lua") {
+        sidecars.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn read_statistics(path: &Path) -> BookyResult<Vec<StatisticsDay>> {
+    let mut connection = database::establish_read_only_connection(path)?;
+    let days = sql_query(
+        "SELECT b.title, b.authors, date(p.start_time, 'unixepoch', 'localtime') AS day,
+            sum(p.duration) AS seconds, count(DISTINCT p.page) AS pages
+        FROM page_stat_data p JOIN book b ON b.id = p.id_book
+        GROUP BY p.id_book, day
+        ORDER BY day",
+    )
+    .load(&mut connection)?;
+    Ok(days)
+}
+
+fn read_sidecar(path: &Path) -> BookyResult<Sidecar> {
+    let values = parse_lua_table(&fs::read_to_string(path)?);
+    let value = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| values.get(*key))
+            .filter(|value| !value.is_empty())
+            .cloned()
+    };
+
+    Ok(Sidecar {
+        title: value(&["doc_props.title", "stats.title"]).unwrap_or_else(|| {
+            // Old sidecars have no title, the folder is named after the book
+            path.parent()
+                .and_then(Path::file_stem)
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        }),
+        authors: value(&["doc_props.authors", "stats.authors"]).unwrap_or_default(),
+        status: value(&["summary.status"]),
+        modified: value(&["summary.modified"])
+            .and_then(|modified| NaiveDate::parse_from_str(&modified, "%Y-%m-%d").ok()),
+    })
+}
+
+/// Reads the values of a table KOReader wrote with its `dump` function.
+///
+/// Every value is keyed by its path, like `summary.status`. KOReader puts
+/// every entry on its own line, which is all this understands, so it is
+/// not a Lua parser.
+fn parse_lua_table(content: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut in_string = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        // A string with a newline goes on over several lines
+        if in_string {
+            in_string = !ends_string(line);
+            continue;
+        }
+
+        if line.starts_with('}') {
+            path.pop();
+            continue;
+        }
+        let Some((key, value)) = line
+            .strip_prefix('[')
+            .and_then(|line| line.split_once("] = "))
+        else {
+            continue;
+        };
+        let key = key.trim_matches('"').to_string();
+        let value = value.trim_end_matches(',');
+
+        if value == "{" {
+            path.push(key);
+        } else if let Some(string) = value.strip_prefix('"') {
+            if !ends_string(string) {
+                in_string = true;
+                continue;
+            }
+            let string = string.strip_suffix('"').unwrap_or(string);
+            values.insert(full_key(&path, &key), unescape(string));
+        } else {
+            values.insert(full_key(&path, &key), value.to_string());
+        }
+    }
+    values
+}
+
+fn full_key(path: &[String], key: &str) -> String {
+    path.iter()
+        .map(String::as_str)
+        .chain([key])
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Whether a line ends with a quote that isn't escaped
+fn ends_string(line: &str) -> bool {
+    let line = line.trim_end_matches(',');
+    let Some(rest) = line.strip_suffix('"') else {
+        return false;
+    };
+    let backslashes = rest.chars().rev().take_while(|c| *c == '\\').count();
+    backslashes % 2 == 0
+}
+
+fn unescape(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+    use crate::dates::DateOrder;
+    use crate::form::BookForm;
+    use diesel::connection::SimpleConnection;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn reading_a_finished_book_again_starts_a_new_session() {
+        let mut library = memory_library();
+        let form = BookForm {
+            title: "Dune".to_string(),
+            author: "Frank Herbert".to_string(),
+            status: "finished".to_string(),
+            start_date: "2020-01-01".to_string(),
+            end_date: "2020-02".to_string(),
+            ..BookForm::default()
+        };
+        let book = library
+            .create_book(&form.to_new_book(DateOrder::default()).unwrap())
+            .unwrap();
+
+        // Noon, so the days are the same in every time zone KOReader might use
+        let folder = std::env::temp_dir().join(format!("booky-koreader-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join(STATISTICS_FILE);
+        let mut statistics = SqliteConnection::establish(&path.display().to_string()).unwrap();
+        statistics
+            .batch_execute(
+                "CREATE TABLE book (id INTEGER PRIMARY KEY, title TEXT, authors TEXT);
+                CREATE TABLE page_stat_data (id_book INTEGER, page INTEGER,
+                    start_time INTEGER, duration INTEGER);
+                INSERT INTO book VALUES (1, 'Dune', 'Frank Herbert');
+                INSERT INTO page_stat_data VALUES (1, 1, 1709294400, 600),
+                    (1, 2, 1709380800, 300);",
+            )
+            .unwrap();
+        drop(statistics);
+
+        let report = import(&mut library, std::slice::from_ref(&folder), false);
+        fs::remove_dir_all(&folder).unwrap();
+        let report = report.unwrap();
+        assert_eq!(report.books_updated, 1);
+        assert_eq!(report.days, 2);
+
+        let sessions = library.sessions(book.id).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].started, date(2020, 1, 1));
+        assert_eq!(sessions[0].finished, date(2020, 2, 1));
+        assert_eq!(sessions[0].finished_precision, DatePrecision::Month);
+        assert_eq!(sessions[1].started, date(2024, 3, 1));
+        assert_eq!(sessions[1].finished, None);
+
+        let book = library.book(book.id).unwrap().unwrap();
+        assert_eq!(book.status, ReadingStatus::Reading);
+        assert_eq!(book.start_date, date(2024, 3, 1));
+        assert_eq!(book.end_date, None);
+    }
+
+    #[test]
+    fn strings_are_keyed_by_their_path() {
+        let values = parse_lua_table(
+            "-- we can read Lua syntax here!\n\
+             return {\n    \
+                 [\"doc_props\"] = {\n        \
+                     [\"title\"] = \"Dune\",\n        \
+                     [\"authors\"] = \"Frank Herbert\",\n    \
+                 },\n    \
+                 [\"percent_finished\"] = 0.5,\n    \
+                 [\"summary\"] = {\n        \
+                     [\"status\"] = \"complete\",\n    \
+                 },\n\
+             }\n",
+        );
+        assert_eq!(values["doc_props.title"], "Dune");
+        assert_eq!(values["doc_props.authors"], "Frank Herbert");
+        assert_eq!(values["percent_finished"], "0.5");
+        assert_eq!(values["summary.status"], "complete");
+    }
+
+    #[test]
+    fn escaped_strings_are_unescaped() {
+        let values = parse_lua_table(
+            "return {\n\
+                 [\"title\"] = \"The \\\"Best\\\" Book\",\n\
+                 [\"path\"] = \"C:\\\\Books\\\\\",\n\
+                 [\"empty\"] = \"\",\n\
+             }\n",
+        );
+        assert_eq!(values["title"], "The \"Best\" Book");
+        assert_eq!(values["path"], "C:\\Books\\");
+        assert_eq!(values["empty"], "");
+    }
+
+    #[test]
+    fn multi_line_strings_are_skipped_whole() {
+        // KOReader writes a newline in a string as a backslash at the end of the line
+        let values = parse_lua_table(
+            "return {\n\
+                 [\"description\"] = \"A desert planet\\\n\
+             }\\\n\
+             [\"title\"] = \"Not a key\",\n\
+             and a \\\"quote\\\"\",\n\
+                 [\"title\"] = \"Dune\",\n\
+             }\n",
+        );
+        assert_eq!(values.get("description"), None);
+        assert_eq!(values["title"], "Dune");
+        assert_eq!(values.len(), 1);
+    }
+}
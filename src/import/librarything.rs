@@ -0,0 +1,122 @@
+use super::csv::{convert_date, import_records, round_rating, value, Headers};
+use super::{ImportReport, Strictness};
+use crate::database::Library;
+use crate::error::BookyResult;
+use crate::form::{BookForm, DATE_FORMAT};
+use crate::status::ReadingStatus;
+use std::io::Read;
+
+/// Imports the tab separated export from LibraryThing.
+///
+/// LibraryThing has no read status, so it is worked out from the
+/// collections and dates of a book. Half star ratings are rounded and the
+/// tags are kept.
+pub fn import(
+    library: &mut Library,
+    reader: impl Read,
+    dry_run: bool,
+) -> BookyResult<ImportReport> {
+    // Values are never quoted, a quote is just part of the title
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = Headers::new(reader.headers()?);
+    let title = Some(headers.require("Title")?);
+    let author = headers.find("Primary Author");
+    let rating = headers.find("Rating");
+    let date_started = headers.find("Date Started");
+    let date_read = headers.find("Date Read");
+    let collections = headers.find("Collections");
+    let tags = headers.find("Tags");
+
+    import_records(
+        library,
+        &mut reader,
+        dry_run,
+        Strictness::Lenient,
+        |record| {
+            let started = value(record, date_started);
+            let finished = value(record, date_read);
+            Ok(BookForm {
+                title: value(record, title).to_string(),
+                author: value(record, author).to_string(),
+                tags: value(record, tags).to_string(),
+                rating: round_rating(value(record, rating), "Rating")?,
+                status: status(value(record, collections), started, finished).to_string(),
+                start_date: convert_date(started, "Date Started", DATE_FORMAT)?,
+                end_date: convert_date(finished, "Date Read", DATE_FORMAT)?,
+                ..BookForm::default()
+            })
+        },
+    )
+}
+
+/// Works out the booky status from the collections a book is in and its dates.
+fn status(collections: &str, started: &str, finished: &str) -> ReadingStatus {
+    let in_collection = |name: &str| {
+        collections
+            .split(',')
+            .any(|collection| collection.trim().eq_ignore_ascii_case(name))
+    };
+
+    if !finished.is_empty() || in_collection("Read but unowned") {
+        ReadingStatus::Finished
+    } else if in_collection("Currently reading") || !started.is_empty() {
+        ReadingStatus::Reading
+    } else {
+        ReadingStatus::WantToRead
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn collections_and_dates_give_the_status() {
+        assert_eq!(
+            status("Your library", "", "2020-01-01"),
+            ReadingStatus::Finished
+        );
+        assert_eq!(status("Read but unowned", "", ""), ReadingStatus::Finished);
+        assert_eq!(
+            status("Your library, Currently reading", "", ""),
+            ReadingStatus::Reading
+        );
+        assert_eq!(
+            status("Your library", "2020-01-01", ""),
+            ReadingStatus::Reading
+        );
+        assert_eq!(status("To read", "", ""), ReadingStatus::WantToRead);
+    }
+
+    #[test]
+    fn tab_separated_rows_become_books() {
+        let mut library = memory_library();
+        let file = "Book Id\tTitle\tPrimary Author\tRating\tDate Started\tDate Read\tCollections\tTags\n\
+            1\t\"Dune\" Messiah\tFrank Herbert\t4.5\t2020-01-01\t2020-02-01\tYour library\tsci-fi, desert\n\
+            2\tEmma\tJane Austen\t\t\t\tCurrently reading\t\n";
+        let report = import(&mut library, file.as_bytes(), false).unwrap();
+        assert_eq!(report.created, 2);
+
+        let books = library.books().unwrap();
+        let messiah = books
+            .iter()
+            .find(|book| book.author == "Frank Herbert")
+            .unwrap();
+        assert_eq!(messiah.title, "\"Dune\" Messiah");
+        assert_eq!(messiah.rating, 5);
+        assert_eq!(messiah.tags, "desert, sci-fi");
+        assert_eq!(messiah.status, ReadingStatus::Finished);
+        assert_eq!(messiah.start_date, NaiveDate::from_ymd_opt(2020, 1, 1));
+        assert_eq!(messiah.end_date, NaiveDate::from_ymd_opt(2020, 2, 1));
+
+        let emma = books.iter().find(|book| book.title == "Emma").unwrap();
+        assert_eq!(emma.status, ReadingStatus::Reading);
+        assert_eq!(emma.rating, 0);
+    }
+}
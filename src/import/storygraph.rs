@@ -0,0 +1,113 @@
+use super::csv::{convert_date, import_records, round_rating, value, Headers};
+use super::{ImportReport, Strictness};
+use crate::database::Library;
+use crate::error::BookyResult;
+use crate::form::BookForm;
+use std::io::Read;
+
+/// Format of the dates in a StoryGraph export.
+const STORYGRAPH_DATE_FORMAT: &str = "%Y/%m/%d";
+
+/// Imports the CSV export from StoryGraph.
+///
+/// Star ratings like `3.75` are rounded to whole stars and the tags and
+/// format are kept. The dates come from the last range in Dates Read.
+pub fn import(
+    library: &mut Library,
+    reader: impl Read,
+    dry_run: bool,
+) -> BookyResult<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = Headers::new(reader.headers()?);
+    let title = Some(headers.require("Title")?);
+    let authors = headers.find("Authors");
+    let read_status = headers.find("Read Status");
+    let rating = headers.find("Star Rating");
+    let last_date_read = headers.find("Last Date Read");
+    let dates_read = headers.find("Dates Read");
+    let tags = headers.find("Tags");
+    let format = headers.find("Format");
+
+    import_records(
+        library,
+        &mut reader,
+        dry_run,
+        Strictness::Lenient,
+        |record| {
+            let (started, finished) = last_read(value(record, dates_read));
+            let (end_column, end_date) = match value(record, last_date_read) {
+                "" => ("Dates Read", finished),
+                end_date => ("Last Date Read", end_date),
+            };
+
+            Ok(BookForm {
+                title: value(record, title).to_string(),
+                author: value(record, authors).to_string(),
+                tags: value(record, tags).to_string(),
+                rating: round_rating(value(record, rating), "Star Rating")?,
+                // read, currently-reading, to-read and did-not-finish are all known to the form
+                status: value(record, read_status).to_string(),
+                format: value(record, format).to_string(),
+                start_date: convert_date(started, "Dates Read", STORYGRAPH_DATE_FORMAT)?,
+                end_date: convert_date(end_date, end_column, STORYGRAPH_DATE_FORMAT)?,
+                ..BookForm::default()
+            })
+        },
+    )
+}
+
+// Dates Read looks like "2023/01/02-2023/04/12, 2024/05/01-2024/05/20",
+// returns the start and end of the last read
+fn last_read(dates_read: &str) -> (&str, &str) {
+    let last = dates_read.rsplit(',').next().unwrap_or_default().trim();
+    match last.split_once('-') {
+        Some((started, finished)) => (started.trim(), finished.trim()),
+        None => ("", last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::memory_library;
+    use crate::status::ReadingStatus;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn the_last_read_gives_the_dates() {
+        assert_eq!(
+            last_read("2023/01/02-2023/04/12, 2024/05/01-2024/05/20"),
+            ("2024/05/01", "2024/05/20")
+        );
+        assert_eq!(last_read("2024/05/20"), ("", "2024/05/20"));
+        assert_eq!(last_read(""), ("", ""));
+    }
+
+    #[test]
+    fn rows_become_books() {
+        let mut library = memory_library();
+        let file = "Title,Authors,ISBN/UID,Format,Read Status,Last Date Read,Dates Read,Star Rating,Tags\n\
+            Dune,Frank Herbert,,paperback,read,,2023/01/02-2023/04/12,3.75,classics\n\
+            Hyperion,Dan Simmons,,audio,currently-reading,,2024/05/01-,,\n\
+            Emma,Jane Austen,,,did-not-finish,,,,\n";
+        let report = import(&mut library, file.as_bytes(), false).unwrap();
+        assert_eq!(report.created, 3);
+
+        let books = library.books().unwrap();
+        let book = |title: &str| books.iter().find(|book| book.title == title).unwrap();
+
+        let dune = book("Dune");
+        assert_eq!(dune.status, ReadingStatus::Finished);
+        assert_eq!(dune.rating, 4);
+        assert_eq!(dune.tags, "classics");
+        assert_eq!(dune.start_date, NaiveDate::from_ymd_opt(2023, 1, 2));
+        assert_eq!(dune.end_date, NaiveDate::from_ymd_opt(2023, 4, 12));
+
+        let hyperion = book("Hyperion");
+        assert_eq!(hyperion.status, ReadingStatus::Reading);
+        assert_eq!(hyperion.start_date, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(hyperion.end_date, None);
+
+        assert_eq!(book("Emma").status, ReadingStatus::Abandoned);
+    }
+}
@@ -1,17 +1,61 @@
 /// Application.
+#[cfg(feature = "tui")]
 pub mod app;
 
 /// Terminal events handler.
+#[cfg(feature = "tui")]
 pub mod event;
 
 /// Widget renderer.
+#[cfg(feature = "tui")]
 pub mod ui;
 
 /// Terminal user interface.
+#[cfg(feature = "tui")]
 pub mod tui;
 
 /// Event handler.
+#[cfg(feature = "tui")]
 pub mod handler;
 
 /// Database
 pub mod database;
+
+/// Splitting author strings into people and their roles.
+pub mod authors;
+
+/// Tags, tag filters and tag completion.
+pub mod tags;
+
+/// Series titles and volume numbers.
+pub mod series;
+
+/// ISBNs and the formats of an edition.
+pub mod editions;
+
+/// Dates that are only known to the month or year.
+pub mod dates;
+
+/// Reading statuses and how they are stored.
+pub mod status;
+
+/// Columns of the book table.
+pub mod columns;
+
+/// Book input shared by the tui and the command line.
+pub mod form;
+
+/// Exporting the library.
+pub mod export;
+
+/// Importing books from other tools and exports.
+pub mod import;
+
+/// Command line interface.
+pub mod cli;
+
+/// User configuration.
+pub mod config;
+
+/// Error types.
+pub mod error;
@@ -1,70 +1,142 @@
-use booky::app::{App, AppResult};
+use booky::cli::{self, Cli, Command};
+use booky::config::{self, Config};
+use booky::database::{self, Library};
+use chrono::Local;
+use clap::Parser;
+use std::error::Error;
+use std::path::Path;
+use std::{fs, process};
 
-use booky::database;
+#[cfg(feature = "tui")]
+use booky::app::{App, AppResult};
+#[cfg(feature = "tui")]
 use booky::event::{Event, EventHandler};
+#[cfg(feature = "tui")]
 use booky::handler::handle_key_events;
-use booky::tui::Tui;
-use diesel::{connection, Connection, SqliteConnection};
-use diesel_migrations::{FileBasedMigrations, MigrationHarness};
-use dirs_2::document_dir;
-use std::error::Error;
-use std::{fs, io};
+#[cfg(feature = "tui")]
+use booky::tui::{init_panic_hook, Tui};
+#[cfg(feature = "tui")]
+use std::io;
+#[cfg(feature = "tui")]
 use tui::backend::CrosstermBackend;
+#[cfg(feature = "tui")]
 use tui::Terminal;
 
-// Checks if db exists in documents folder, if it
-// doesn't it will create one and return the string
-fn dir_init() -> Result<String, Box<dyn Error + 'static>> {
-    let booky_dir = document_dir()
-        .expect("Failed to create booky directory in /Documents")
-        .join("booky");
-
-    if !booky_dir.exists() {
-        fs::create_dir(booky_dir).expect("Failed to create booky directory");
+// Creates the directory the database lives in, sqlite
+// creates the database file itself on the first connection
+fn dir_init(db_path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
     }
-
-    let document_path = document_dir()
-        .expect("Failed to find /Documents")
-        .join("booky")
-        .join("books.db");
-
-    Ok(document_path.display().to_string())
+    Ok(())
 }
 
-fn main() -> AppResult<()> {
-    if let Ok(s) = dir_init() {
-        let connection = &mut SqliteConnection::establish(&s).expect("Could not connect to db");
-        let migrations =
-            FileBasedMigrations::find_migrations_directory().expect("Could not find migrations");
-        connection
-            .run_pending_migrations(migrations)
-            .expect("Error running migrations");
+#[cfg(feature = "tui")]
+fn run_tui(tui: &mut Tui<CrosstermBackend<io::Stderr>>, app: &mut App) -> AppResult<()> {
+    // Start the main loop.
+    while app.running {
+        // Quit like the user pressed q so the terminal gets restored.
+        if tui.should_terminate() {
+            app.quit();
+            break;
+        }
+        // Render the user interface.
+        tui.draw(app)?;
+        // Handle events.
+        match tui.events.next()? {
+            Event::Tick => app.tick(),
+            Event::Key(key_event) => handle_key_events(key_event, app)?,
+            Event::Mouse(_) => {}
+            Event::Resize(_, _) => {}
+        }
     }
+    Ok(())
+}
 
+#[cfg(feature = "tui")]
+fn start_tui(library: Library, config: &Config, db_path: &Path) -> AppResult<()> {
     // Create an application.
-    let mut app = App::new();
+    let mut app = App::new(library);
+    app.columns = config.columns();
+    app.date_order = config.date_order;
+    app.reload()?;
 
     // Initialize the terminal user interface.
+    init_panic_hook(db_path.with_file_name("crash.log"));
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(250);
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
-    // Start the main loop.
-    while app.running {
-        // Render the user interface.
-        tui.draw(&mut app)?;
-        // Handle events.
-        match tui.events.next()? {
-            Event::Tick => app.tick(),
-            Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
-            Event::Mouse(_) => {}
-            Event::Resize(_, _) => {}
+    let result = run_tui(&mut tui, &mut app);
+
+    // Exit the user interface, also when the main loop failed.
+    tui.exit()?;
+    result
+}
+
+#[cfg(not(feature = "tui"))]
+fn start_tui(_library: Library, _config: &Config, _db_path: &Path) -> Result<(), Box<dyn Error>> {
+    Err(
+        "booky was built without the tui feature, see booky --help for the available commands"
+            .into(),
+    )
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    let config = Config::load()?;
+    let path = config.db_path(cli.db)?;
+    if config::legacy_db_path().is_some_and(|legacy| legacy == path) {
+        eprintln!(
+            "Using the database in {}, move it to {} to keep it in the data directory",
+            path.display(),
+            config::data_db_path()?.display()
+        );
+    }
+    dir_init(&path)?;
+    let mut library = Library::new(database::establish_connection(&path)?);
+
+    // `booky db` manages the migrations itself, everything
+    // else needs an up to date database
+    if !matches!(cli.command, Some(Command::Db { .. })) {
+        let applied = library.run_migrations()?;
+        if let Some(version) = applied.last() {
+            eprintln!(
+                "Applied {} migration(s), schema version is now {version}",
+                applied.len()
+            );
+        }
+
+        // Purging would be a new change, undo would only ever take that back
+        let undoing = matches!(
+            cli.command,
+            Some(Command::Undo | Command::Redo | Command::History { .. })
+        );
+        if let Some(retention) = config.trash_retention().filter(|_| !undoing) {
+            let purged = library.purge_deleted_before(Local::now().naive_local() - retention)?;
+            if purged > 0 {
+                eprintln!(
+                    "Purged {} book(s) that were in the trash for more than {} days",
+                    purged,
+                    retention.num_days()
+                );
+            }
         }
     }
 
-    // Exit the user interface.
-    tui.exit()?;
-    Ok(())
+    match cli.command {
+        Some(command) => Ok(cli::run(command, &mut library, &config)?),
+        None => start_tui(library, &config, &path),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("booky: {e}");
+        process::exit(1);
+    }
 }
@@ -0,0 +1,98 @@
+/// Splits the series off a title like "Oathbringer (The Stormlight Archive, #3)".
+///
+/// Returns the title without the series, and the series with the number of
+/// the volume. This is how Goodreads writes titles and how they used to be
+/// typed into booky, a title without `#number` in its last parentheses is
+/// returned as it is.
+pub fn split_series_title(title: &str) -> (String, Option<(String, f64)>) {
+    let series = title
+        .trim_end()
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .and_then(|(title, inner)| {
+            let (name, index) = inner.rsplit_once('#')?;
+            let index = index.trim().parse::<f64>().ok()?;
+            let name = name.trim_end().trim_end_matches(',').trim();
+            let title = title.trim();
+            (!name.is_empty() && !title.is_empty()).then_some((title, name, index))
+        });
+
+    match series {
+        Some((title, name, index)) => (title.to_string(), Some((name.to_string(), index))),
+        None => (title.to_string(), None),
+    }
+}
+
+/// Formats the number of a volume, whole numbers without a fraction.
+pub fn format_index(index: f64) -> String {
+    if index.fract() == 0.0 {
+        format!("{}", index as i64)
+    } else {
+        index.to_string()
+    }
+}
+
+/// Formats the series of a book like "The Stormlight Archive #3".
+pub fn format_series(series: Option<&str>, index: Option<f64>) -> String {
+    match (series, index) {
+        (Some(series), Some(index)) => format!("{} #{}", series, format_index(index)),
+        (Some(series), None) => series.to_string(),
+        (None, _) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(name: &str, index: f64) -> Option<(String, f64)> {
+        Some((name.to_string(), index))
+    }
+
+    #[test]
+    fn the_series_is_split_off_the_title() {
+        assert_eq!(
+            split_series_title("Oathbringer (The Stormlight Archive, #3)"),
+            (
+                "Oathbringer".to_string(),
+                series("The Stormlight Archive", 3.0)
+            )
+        );
+        assert_eq!(
+            split_series_title("Edgedancer (The Stormlight Archive #2.5) "),
+            (
+                "Edgedancer".to_string(),
+                series("The Stormlight Archive", 2.5)
+            )
+        );
+        // Only the last parentheses hold the series
+        assert_eq!(
+            split_series_title("Mort (Discworld) (Discworld, #4)"),
+            ("Mort (Discworld)".to_string(), series("Discworld", 4.0))
+        );
+    }
+
+    #[test]
+    fn titles_without_a_volume_are_kept() {
+        for title in [
+            "Dune",
+            "Dune (Dune Chronicles)",
+            "Dune (Dune Chronicles, #one)",
+            "(Dune Chronicles, #1)",
+            "Dune (#1)",
+        ] {
+            assert_eq!(split_series_title(title), (title.to_string(), None));
+        }
+    }
+
+    #[test]
+    fn series_are_formatted_with_their_volume() {
+        assert_eq!(format_series(Some("Discworld"), Some(4.0)), "Discworld #4");
+        assert_eq!(
+            format_series(Some("Stormlight"), Some(2.5)),
+            "Stormlight #2.5"
+        );
+        assert_eq!(format_series(Some("Discworld"), None), "Discworld");
+        assert_eq!(format_series(None, Some(4.0)), "");
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::{BookyError, BookyResult};
+use chrono::NaiveDate;
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a book is in the reading process.
+///
+/// It is stored as its [`ReadingStatus::as_str`] in the text column
+/// `books.status`, reading any other value from the database is an error.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "kebab-case", try_from = "String")]
+pub enum ReadingStatus {
+    #[default]
+    WantToRead,
+    Reading,
+    /// Put away for now, but not given up.
+    Paused,
+    Finished,
+    Abandoned,
+}
+
+impl ReadingStatus {
+    /// Every status in the order a book goes through them.
+    pub const ALL: [ReadingStatus; 5] = [
+        ReadingStatus::WantToRead,
+        ReadingStatus::Reading,
+        ReadingStatus::Paused,
+        ReadingStatus::Finished,
+        ReadingStatus::Abandoned,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReadingStatus::WantToRead => "want-to-read",
+            ReadingStatus::Reading => "reading",
+            ReadingStatus::Paused => "paused",
+            ReadingStatus::Finished => "finished",
+            ReadingStatus::Abandoned => "abandoned",
+        }
+    }
+
+    /// Reads a status the way people and other tools write it.
+    ///
+    /// Case, spaces and underscores are ignored and common names like "read",
+    /// "to-read" or "dnf" are understood, `None` when it is no status at all.
+    pub fn parse(text: &str) -> Option<Self> {
+        let status = text.trim().to_lowercase().replace([' ', '_'], "-");
+        match status.as_str() {
+            "want-to-read" | "to-read" | "tbr" | "unread" => Some(ReadingStatus::WantToRead),
+            "reading" | "currently-reading" | "started" | "in-progress" => {
+                Some(ReadingStatus::Reading)
+            }
+            "paused" | "on-hold" => Some(ReadingStatus::Paused),
+            "finished" | "read" | "done" | "completed" | "complete" => {
+                Some(ReadingStatus::Finished)
+            }
+            "abandoned" | "dnf" | "did-not-finish" | "dropped" => Some(ReadingStatus::Abandoned),
+            _ => None,
+        }
+    }
+
+    /// The status of a book that was given dates but no status.
+    pub fn from_dates(start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> Self {
+        match (start_date, end_date) {
+            (_, Some(_)) => ReadingStatus::Finished,
+            (Some(_), None) => ReadingStatus::Reading,
+            (None, None) => ReadingStatus::WantToRead,
+        }
+    }
+
+    /// The status after this one in [`ReadingStatus::ALL`], the last one wraps around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|status| *status == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+
+    /// The status before this one in [`ReadingStatus::ALL`], the first one wraps around.
+    pub fn previous(self) -> Self {
+        let index = Self::ALL.iter().position(|status| *status == self);
+        let len = Self::ALL.len();
+        Self::ALL[index.map_or(0, |index| (index + len - 1) % len)]
+    }
+}
+
+impl fmt::Display for ReadingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReadingStatus {
+    type Err = BookyError;
+
+    fn from_str(s: &str) -> BookyResult<Self> {
+        Self::parse(s).ok_or_else(|| {
+            let statuses: Vec<&str> = Self::ALL.iter().map(|status| status.as_str()).collect();
+            BookyError::Validation(format!(
+                "Status must be one of {}, got \"{}\"",
+                statuses.join(", "),
+                s.trim()
+            ))
+        })
+    }
+}
+
+// Exports from before the status was checked can have an empty one
+impl TryFrom<String> for ReadingStatus {
+    type Error = BookyError;
+
+    fn try_from(value: String) -> BookyResult<Self> {
+        match value.trim() {
+            "" => Ok(ReadingStatus::default()),
+            value => value.parse(),
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for ReadingStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.as_str());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for ReadingStatus {
+    fn from_sql(bytes: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        ReadingStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| format!("Unknown reading status \"{}\" in the database", value).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::dsl::sql;
+    use diesel::{Connection, RunQueryDsl, SqliteConnection};
+
+    #[test]
+    fn common_names_are_understood() {
+        for (text, status) in [
+            ("Read", ReadingStatus::Finished),
+            ("done", ReadingStatus::Finished),
+            ("to read", ReadingStatus::WantToRead),
+            ("to_read", ReadingStatus::WantToRead),
+            ("Currently Reading", ReadingStatus::Reading),
+            ("on hold", ReadingStatus::Paused),
+            ("DNF", ReadingStatus::Abandoned),
+        ] {
+            assert_eq!(text.parse::<ReadingStatus>().unwrap(), status, "{}", text);
+        }
+    }
+
+    #[test]
+    fn every_status_parses_back_from_its_name() {
+        for status in ReadingStatus::ALL {
+            assert_eq!(status.to_string().parse::<ReadingStatus>().unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_errors() {
+        let error = "someday".parse::<ReadingStatus>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Status must be one of want-to-read, reading, paused, finished, abandoned, got \"someday\""
+        );
+        assert!("".parse::<ReadingStatus>().is_err());
+    }
+
+    #[test]
+    fn the_database_only_holds_exact_names() {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        let mut load = |value: &str| {
+            diesel::select(sql::<Text>(&format!("'{}'", value)))
+                .get_result::<ReadingStatus>(&mut connection)
+        };
+        assert_eq!(load("want-to-read").unwrap(), ReadingStatus::WantToRead);
+        // Aliases are for people, the database is written by booky
+        assert!(load("read").is_err());
+        assert!(load("Finished").is_err());
+    }
+}
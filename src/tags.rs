@@ -0,0 +1,97 @@
+/// Splits a tag string like "fantasy, book club" into its tags.
+///
+/// Tags are separated by commas. Spaces around a tag are dropped and a tag
+/// that differs from an earlier one only in case is left out, the same way
+/// the database treats them.
+pub fn split_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',') {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !tag.is_empty() && !tags.iter().any(|known| known.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Writes tags the way [`split_tags`] reads them.
+pub fn join_tags<S: AsRef<str>>(tags: &[S]) -> String {
+    tags.iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the known tags that start with the tag being typed at the end of `text`.
+///
+/// Tags that are already in `text` aren't suggested again.
+pub fn complete_tag<'a>(known: &'a [String], text: &str) -> Vec<&'a str> {
+    let (typed, partial) = split_partial(text);
+    let partial = partial.trim_start().to_lowercase();
+    if partial.is_empty() {
+        return Vec::new();
+    }
+
+    let typed = split_tags(typed);
+    known
+        .iter()
+        .filter(|tag| tag.to_lowercase().starts_with(&partial))
+        .filter(|tag| !typed.iter().any(|typed| typed.eq_ignore_ascii_case(tag)))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Replaces the tag being typed at the end of `text` with `tag`.
+///
+/// A comma is added so the next tag can be typed right away.
+pub fn accept_tag(text: &str, tag: &str) -> String {
+    let (typed, _) = split_partial(text);
+    let mut tags = split_tags(typed);
+    tags.push(tag.to_string());
+    format!("{}, ", join_tags(&tags))
+}
+
+// "fantasy, book c" is ("fantasy", " book c")
+fn split_partial(text: &str) -> (&str, &str) {
+    text.rsplit_once(',').unwrap_or(("", text))
+}
+
+/// How the tags of a [`TagFilter`] have to match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    /// A book needs at least one of the tags.
+    #[default]
+    Any,
+    /// A book needs every tag.
+    All,
+}
+
+impl TagMatch {
+    pub fn toggle(self) -> Self {
+        match self {
+            TagMatch::Any => TagMatch::All,
+            TagMatch::All => TagMatch::Any,
+        }
+    }
+}
+
+/// Limits a search to books with some tags, an empty filter matches every book.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub mode: TagMatch,
+}
+
+impl TagFilter {
+    /// Parses a tag string like "fantasy, book club" into a filter.
+    pub fn new(text: &str, mode: TagMatch) -> Self {
+        Self {
+            tags: split_tags(text),
+            mode,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
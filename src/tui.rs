@@ -1,9 +1,18 @@
 use crate::app::{App, AppResult};
 use crate::event::EventHandler;
 use crate::ui;
+use crossterm::cursor::Show;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
-use std::io;
+use signal_hook::consts::TERM_SIGNALS;
+use std::backtrace::Backtrace;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tui::backend::Backend;
 use tui::Terminal;
 
@@ -17,18 +26,29 @@ pub struct Tui<B: Backend> {
     terminal: Terminal<B>,
     /// Terminal event handler.
     pub events: EventHandler,
+    /// Set when booky receives SIGTERM, SIGINT or SIGQUIT.
+    terminate: Arc<AtomicBool>,
 }
 
 impl<B: Backend> Tui<B> {
     /// Constructs a new instance of [`Tui`].
     pub fn new(terminal: Terminal<B>, events: EventHandler) -> Self {
-        Self { terminal, events }
+        Self {
+            terminal,
+            events,
+            terminate: Arc::new(AtomicBool::new(false)),
+        }
     }
 
     /// Initializes the terminal interface.
     ///
     /// It enables the raw mode and sets terminal properties.
+    /// Termination signals are caught so the main loop can
+    /// quit and restore the terminal, see [`Tui::should_terminate`].
     pub fn init(&mut self) -> AppResult<()> {
+        for signal in TERM_SIGNALS {
+            signal_hook::flag::register(*signal, Arc::clone(&self.terminate))?;
+        }
         terminal::enable_raw_mode()?;
         crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
         self.terminal.hide_cursor()?;
@@ -36,6 +56,11 @@ impl<B: Backend> Tui<B> {
         Ok(())
     }
 
+    /// Returns true once a termination signal was received.
+    pub fn should_terminate(&self) -> bool {
+        self.terminate.load(Ordering::Relaxed)
+    }
+
     /// [`Draw`] the terminal interface by [`rendering`] the widgets.
     ///
     /// [`Draw`]: tui::Terminal::draw
@@ -49,9 +74,59 @@ impl<B: Backend> Tui<B> {
     ///
     /// It disables the raw mode and reverts back the terminal properties.
     pub fn exit(&mut self) -> AppResult<()> {
-        terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        reset()?;
         self.terminal.show_cursor()?;
         Ok(())
     }
 }
+
+/// Disables the raw mode and leaves the alternate screen.
+///
+/// This doesn't need a [`Tui`] so it can be used from the panic hook.
+pub fn reset() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        io::stderr(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        Show
+    )
+}
+
+/// Installs a panic hook that restores the terminal before anything is printed.
+///
+/// The panic message and a backtrace are appended to `crash_log`,
+/// afterwards booky exits since the terminal is no longer usable.
+pub fn init_panic_hook(crash_log: PathBuf) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = reset();
+
+        let report = format!(
+            "booky {} crashed at {}\n{}\n\nBacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            info,
+            Backtrace::force_capture()
+        );
+        match write_crash_log(&crash_log, &report) {
+            Ok(()) => {
+                default_hook(info);
+                eprintln!(
+                    "booky crashed, a crash report was written to {}",
+                    crash_log.display()
+                );
+            }
+            Err(e) => {
+                eprint!("{report}");
+                eprintln!("Could not write {}: {}", crash_log.display(), e);
+            }
+        }
+        process::exit(1);
+    }));
+}
+
+fn write_crash_log(path: &Path, report: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{report}")
+}
@@ -1,16 +1,16 @@
-use crate::app::{App, BookEditFocus, SearchFieldFocus};
-use crate::database;
+use crate::app::{App, BookEditFocus, LogMessage, SearchFieldFocus};
+use crate::series::format_index;
+use crate::tags::TagMatch;
 use tui::{
-    backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Spans,
-    widgets::{Block, Borders, Clear, Paragraph, Row, Table},
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table},
     Frame,
 };
 
 /// Renders the user interface widgets.
-pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+pub fn render(app: &mut App, frame: &mut Frame) {
     // Render header, body and footer of booky.
     render_main(app, frame);
 
@@ -18,9 +18,12 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
     render_add_popup(app, frame);
     render_help_popup(app, frame);
     render_search_popup(app, frame);
+    render_series_popup(app, frame);
+    render_delete_popup(app, frame);
+    render_trash_popup(app, frame);
 }
 
-fn render_main<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+fn render_main(app: &mut App, frame: &mut Frame) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(5)
@@ -32,83 +35,54 @@ fn render_main<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
             ]
             .as_ref(),
         )
-        .split(frame.size());
+        .split(frame.area());
 
     // Show stats temp on the top
     let menu_block = Block::default()
         .title("Booky")
         .title_alignment(Alignment::Center)
         .borders(Borders::ALL);
-    let books_count = Spans::from(format!("Total: {}", app.items.len()));
+    let books_count = Line::from(format!("Total: {}", app.items.len()));
 
     let stats_block = Paragraph::new(books_count).block(menu_block);
     frame.render_widget(stats_block, chunks[0]);
 
-    if !app.search_active {
-        app.items = database::get_books(app);
-    }
     let rows: Vec<Row> = app
         .items
         .iter()
-        .map(|i| {
-            Row::new(vec![
-                i.id.to_string(),
-                i.title.to_string(),
-                i.author.to_string(),
-                i.genre.to_string(),
-                i.rating.to_string(),
-                i.status.to_string(),
-                i.start_date.unwrap().to_string(),
-                i.end_date.unwrap().to_string(),
-                i.end_date
-                    .unwrap()
-                    .signed_duration_since(i.start_date.unwrap())
-                    .num_days()
-                    .to_string(),
-            ])
-        })
+        .map(|i| Row::new(app.columns.iter().map(|column| column.value(i))))
+        .collect();
+    let headers = Row::new(app.columns.iter().map(|column| column.header()));
+    let widths: Vec<Constraint> = app
+        .columns
+        .iter()
+        .map(|column| Constraint::Length(column.width()))
         .collect();
-    let headers = Row::new(vec![
-        "Id",
-        "Title",
-        "Author",
-        "Genre",
-        "Rating",
-        "Status",
-        "StartDate",
-        "EndDate",
-        "Finished (days)",
-    ]);
-
-    let table = Table::new(rows)
+
+    let table = Table::new(rows, widths)
         .header(headers.style(Style::default().fg(Color::Yellow)))
         .block(Block::default().borders(Borders::ALL))
         .column_spacing(3)
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
-        .highlight_symbol(">>")
-        .widths(&[
-            Constraint::Length(2),
-            Constraint::Length(20),
-            Constraint::Length(20),
-            Constraint::Length(10),
-            Constraint::Length(10),
-            Constraint::Length(10),
-            Constraint::Length(10),
-            Constraint::Length(10),
-            Constraint::Length(15),
-        ]);
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol(">>");
 
     frame.render_stateful_widget(table, chunks[1], &mut app.state);
 
     let footer = Block::default().title("Log").borders(Borders::ALL);
-    let temp_info = Paragraph::new("Press ? to access the help menu").block(footer);
-    frame.render_widget(temp_info, chunks[2]);
+    let log = match &app.log {
+        Some(LogMessage::Info(message)) => Paragraph::new(message.as_str()),
+        Some(LogMessage::Error(message)) => {
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red))
+        }
+        None => Paragraph::new("Press ? to access the help menu"),
+    };
+    frame.render_widget(log.block(footer), chunks[2]);
 }
 
-fn render_add_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+fn render_add_popup(app: &mut App, frame: &mut Frame) {
     if app.add_popup {
         let block = Block::default().title("Add New Book").borders(Borders::ALL);
-        let area = centered_rect(45, 60, frame.size());
+        let area = centered_rect(50, 75, frame.area());
         let block_inner = block.inner(area);
         frame.render_widget(Clear, area);
         frame.render_widget(Paragraph::new("").block(block), area);
@@ -120,6 +94,9 @@ fn render_add_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                         Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Length(3),
+                        Constraint::Length(1),
+                        Constraint::Length(3),
+                        Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Length(3),
@@ -141,7 +118,7 @@ fn render_add_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                     ]
                     .as_ref(),
                 )
-                .split(layout[8]);
+                .split(layout[11]);
 
             let (create_style, cancel_style, create_txt, cancel_txt) = match task.focus {
                 BookEditFocus::ConfirmBtn => (
@@ -165,122 +142,166 @@ fn render_add_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
             frame.render_widget(cancel_btn, buttons[2]);
 
             let tab_info = Paragraph::new("[tab] / [shift + tab] to cycle");
-            frame.render_widget(tab_info, layout[7]);
-
-            let b1 = Block::default().title("Title").borders(Borders::ALL);
-            let b2 = Block::default().title("Author").borders(Borders::ALL);
-            let b3 = Block::default().title("Genre").borders(Borders::ALL);
-            let b4 = Block::default().title("Rating").borders(Borders::ALL);
-            let b5 = Block::default().title("Status").borders(Borders::ALL);
-            let b6 = Block::default()
-                .title("StartDate (year/month/day)")
-                .borders(Borders::ALL);
-            let b7 = Block::default()
-                .title("EndDate (year/month/day)")
-                .borders(Borders::ALL);
-
-            task.title.set_cursor_line_style(Style::default());
-            task.author.set_cursor_line_style(Style::default());
-            task.genre.set_cursor_line_style(Style::default());
-            task.rating.set_cursor_line_style(Style::default());
-            task.status.set_cursor_line_style(Style::default());
-            task.start_date.set_cursor_line_style(Style::default());
-            task.end_date.set_cursor_line_style(Style::default());
-
-            task.title.set_block(b1);
-
-            if let BookEditFocus::Title = task.focus {
-                task.title
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.title
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED))
-            } else {
-                task.title.set_style(Style::default());
-                task.title.set_cursor_style(Style::default());
+            frame.render_widget(tab_info, layout[10]);
+
+            // Known tags that start with what is being typed
+            if let BookEditFocus::Tags = task.focus {
+                let suggestions = task.tag_suggestions(&app.tags);
+                if !suggestions.is_empty() {
+                    let hint = Paragraph::new(format!("[right] {}", suggestions.join(" | ")))
+                        .style(Style::default().fg(Color::DarkGray));
+                    frame.render_widget(hint, layout[3]);
+                }
             }
-            frame.render_widget(task.title.widget(), layout[0]);
 
-            task.author.set_block(b2);
-            if let BookEditFocus::Author = task.focus {
-                task.author
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.author
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.author.set_style(Style::default());
-                task.author.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.author.widget(), layout[1]);
-
-            task.genre.set_block(b3);
-            if let BookEditFocus::Genre = task.focus {
-                task.genre
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.genre
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.genre.set_style(Style::default());
-                task.genre.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.genre.widget(), layout[2]);
-
-            task.rating.set_block(b4);
-            if let BookEditFocus::Rating = task.focus {
-                task.rating
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.rating
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.rating.set_style(Style::default());
-                task.rating.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.rating.widget(), layout[3]);
-
-            task.status.set_block(b5);
-            if let BookEditFocus::Status = task.focus {
-                task.status
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.status
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.status.set_style(Style::default());
-                task.status.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.status.widget(), layout[4]);
-
-            task.start_date.set_block(b6);
-            if let BookEditFocus::StartDate = task.focus {
-                task.start_date
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.start_date
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.start_date.set_style(Style::default());
-                task.start_date.set_cursor_style(Style::default());
+            // Short fields share a row, the first one gets `percent` of it
+            let split_row = |area: Rect, percent: u16| {
+                Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [
+                            Constraint::Percentage(percent),
+                            Constraint::Percentage(100 - percent),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(area)
+            };
+            let series_row = split_row(layout[4], 75);
+            let isbn_row = split_row(layout[5], 60);
+            let publisher_row = split_row(layout[6], 75);
+            let language_row = split_row(layout[7], 60);
+            let rating_row = split_row(layout[8], 50);
+            let dates_row = split_row(layout[9], 50);
+
+            let focus = task.focus;
+            let fields = [
+                (&mut task.title, "Title", BookEditFocus::Title, layout[0]),
+                (&mut task.author, "Author", BookEditFocus::Author, layout[1]),
+                (
+                    &mut task.tags,
+                    "Tags (comma separated)",
+                    BookEditFocus::Tags,
+                    layout[2],
+                ),
+                (
+                    &mut task.series,
+                    "Series",
+                    BookEditFocus::Series,
+                    series_row[0],
+                ),
+                (
+                    &mut task.series_index,
+                    "#",
+                    BookEditFocus::SeriesIndex,
+                    series_row[1],
+                ),
+                (&mut task.isbn, "ISBN", BookEditFocus::Isbn, isbn_row[0]),
+                (
+                    &mut task.format,
+                    "Format (hardcover/paperback/ebook/audiobook)",
+                    BookEditFocus::Format,
+                    isbn_row[1],
+                ),
+                (
+                    &mut task.publisher,
+                    "Publisher",
+                    BookEditFocus::Publisher,
+                    publisher_row[0],
+                ),
+                (
+                    &mut task.published_year,
+                    "Year",
+                    BookEditFocus::PublishedYear,
+                    publisher_row[1],
+                ),
+                (
+                    &mut task.language,
+                    "Language",
+                    BookEditFocus::Language,
+                    language_row[0],
+                ),
+                (
+                    &mut task.pages,
+                    "Pages",
+                    BookEditFocus::Pages,
+                    language_row[1],
+                ),
+                (
+                    &mut task.rating,
+                    "Rating",
+                    BookEditFocus::Rating,
+                    rating_row[0],
+                ),
+                (
+                    &mut task.start_date,
+                    "StartDate (year/month/day)",
+                    BookEditFocus::StartDate,
+                    dates_row[0],
+                ),
+                (
+                    &mut task.end_date,
+                    "EndDate (year/month/day)",
+                    BookEditFocus::EndDate,
+                    dates_row[1],
+                ),
+            ];
+            for (textarea, title, field, area) in fields {
+                let error = field.field().and_then(|field| task.errors.get(field));
+                let border_style = match error {
+                    Some(_) => Style::default().fg(Color::Red),
+                    None => Style::default(),
+                };
+                textarea.set_block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL)
+                        .border_style(border_style),
+                );
+                textarea.set_cursor_line_style(Style::default());
+                if field == focus {
+                    textarea.set_style(Style::default().add_modifier(Modifier::BOLD));
+                    textarea.set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
+                } else {
+                    textarea.set_style(Style::default());
+                    textarea.set_cursor_style(Style::default());
+                }
+                frame.render_widget(&*textarea, area);
+
+                // The error goes on the bottom border, beneath what was typed
+                if let Some(error) = error {
+                    let error_area = Rect {
+                        x: area.x + 1,
+                        y: area.bottom().saturating_sub(1),
+                        width: area.width.saturating_sub(2),
+                        height: 1,
+                    };
+                    let error = Paragraph::new(error).style(Style::default().fg(Color::Red));
+                    frame.render_widget(error, error_area);
+                }
             }
-            frame.render_widget(task.start_date.widget(), layout[5]);
 
-            task.end_date.set_block(b7);
-            if let BookEditFocus::EndDate = task.focus {
-                task.end_date
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.end_date
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-            } else {
-                task.end_date.set_style(Style::default());
-                task.end_date.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.end_date.widget(), layout[6]);
+            let status_style = match focus {
+                BookEditFocus::Status => Style::default().add_modifier(Modifier::BOLD),
+                _ => Style::default(),
+            };
+            let b_status = Block::default()
+                .title("Status [left/right]")
+                .borders(Borders::ALL);
+            let status = Paragraph::new(format!("< {} >", task.status))
+                .style(status_style)
+                .block(b_status);
+            frame.render_widget(status, rating_row[1]);
         }
     }
 }
 
-fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+fn render_search_popup(app: &mut App, frame: &mut Frame) {
     if app.search_popup {
         let block = Block::default()
             .title("Search/Filter")
             .borders(Borders::ALL);
-        let area = centered_rect(40, 55, frame.size());
+        let area = centered_rect(40, 65, frame.area());
         let block_inner = block.inner(area);
         frame.render_widget(Clear, area);
         frame.render_widget(Paragraph::new("").block(block), area);
@@ -296,6 +317,7 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                         Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Length(3),
+                        Constraint::Length(3),
                         Constraint::Length(1),
                         Constraint::Length(2),
                     ]
@@ -313,7 +335,7 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                     ]
                     .as_ref(),
                 )
-                .split(layout[7]);
+                .split(layout[8]);
 
             let (create_style, cancel_style, create_txt, cancel_txt) = match task.focus {
                 SearchFieldFocus::ConfirmBtn => (
@@ -351,7 +373,7 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 task.title.set_style(Style::default());
                 task.title.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.title.widget(), layout[0]);
+            frame.render_widget(&task.title, layout[0]);
 
             let b2 = Block::default().title("Author").borders(Borders::ALL);
 
@@ -368,24 +390,40 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 task.author.set_style(Style::default());
                 task.author.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.author.widget(), layout[1]);
+            frame.render_widget(&task.author, layout[1]);
 
-            let b3 = Block::default().title("Genre").borders(Borders::ALL);
+            let b3 = Block::default()
+                .title("Tags (comma separated)")
+                .borders(Borders::ALL);
 
-            task.genre.set_cursor_line_style(Style::default());
+            task.tags.set_cursor_line_style(Style::default());
 
-            task.genre.set_block(b3);
+            task.tags.set_block(b3);
 
-            if let SearchFieldFocus::Genre = task.focus {
-                task.genre
+            if let SearchFieldFocus::Tags = task.focus {
+                task.tags
                     .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.genre
+                task.tags
                     .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED))
             } else {
-                task.genre.set_style(Style::default());
-                task.genre.set_cursor_style(Style::default());
+                task.tags.set_style(Style::default());
+                task.tags.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.genre.widget(), layout[2]);
+            frame.render_widget(&task.tags, layout[2]);
+
+            let match_text = match task.tag_match {
+                TagMatch::Any => "(x) any of these tags   ( ) all of them",
+                TagMatch::All => "( ) any of these tags   (x) all of them",
+            };
+            let match_style = match task.focus {
+                SearchFieldFocus::TagMatch => Style::default().add_modifier(Modifier::BOLD),
+                _ => Style::default(),
+            };
+            let b_match = Block::default()
+                .title("Match [space]")
+                .borders(Borders::ALL);
+            let tag_match = Paragraph::new(match_text).style(match_style).block(b_match);
+            frame.render_widget(tag_match, layout[3]);
 
             let b4 = Block::default().title("Rating").borders(Borders::ALL);
 
@@ -402,24 +440,21 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 task.rating.set_style(Style::default());
                 task.rating.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.rating.widget(), layout[3]);
-
-            let b5 = Block::default().title("Status").borders(Borders::ALL);
-
-            task.status.set_cursor_line_style(Style::default());
-
-            task.status.set_block(b5);
+            frame.render_widget(&task.rating, layout[4]);
 
-            if let SearchFieldFocus::Status = task.focus {
-                task.status
-                    .set_style(Style::default().add_modifier(Modifier::BOLD));
-                task.status
-                    .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED))
-            } else {
-                task.status.set_style(Style::default());
-                task.status.set_cursor_style(Style::default());
-            }
-            frame.render_widget(task.status.widget(), layout[4]);
+            let status_text = match task.status {
+                Some(status) => format!("< {} >", status),
+                None => "< any status >".to_string(),
+            };
+            let status_style = match task.focus {
+                SearchFieldFocus::Status => Style::default().add_modifier(Modifier::BOLD),
+                _ => Style::default(),
+            };
+            let b5 = Block::default()
+                .title("Status [left/right]")
+                .borders(Borders::ALL);
+            let status = Paragraph::new(status_text).style(status_style).block(b5);
+            frame.render_widget(status, layout[5]);
 
             let b6 = Block::default()
                 .title("StartDate (year/month/day)")
@@ -438,7 +473,7 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 task.start_date.set_style(Style::default());
                 task.start_date.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.start_date.widget(), layout[5]);
+            frame.render_widget(&task.start_date, layout[6]);
 
             let b7 = Block::default()
                 .title("EndDate (year/month/day)")
@@ -457,15 +492,15 @@ fn render_search_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                 task.end_date.set_style(Style::default());
                 task.end_date.set_cursor_style(Style::default());
             }
-            frame.render_widget(task.end_date.widget(), layout[6]);
+            frame.render_widget(&task.end_date, layout[7]);
         }
     }
 }
 
-fn render_help_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
+fn render_help_popup(app: &mut App, frame: &mut Frame) {
     if app.help_popup {
         let block = Block::default().title("Help").borders(Borders::ALL);
-        let area = centered_rect(60, 40, frame.size());
+        let area = centered_rect(60, 40, frame.area());
         let block_inner = block.inner(area);
         frame.render_widget(Clear, area);
         frame.render_widget(Paragraph::new("").block(block), area);
@@ -481,18 +516,26 @@ fn render_help_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
                     Constraint::Length(1),
                     Constraint::Length(1),
                     Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
                 ]
                 .as_ref(),
             )
             .split(block_inner);
 
         let p1 = Paragraph::new("i -> Add a new book");
-        let p2 = Paragraph::new("d -> Delete current highlighted book (no confirmation)");
+        let p2 = Paragraph::new("d -> Move current highlighted book to the trash");
         let p3 = Paragraph::new("jk/updown arrows -> to select a book");
         let p4 = Paragraph::new("u -> Update current selected book");
         let p5 = Paragraph::new("/ -> Search for a book");
         let p6 = Paragraph::new("r -> Clear search");
-        let p7 = Paragraph::new("q -> Quit");
+        let p7 = Paragraph::new("s -> Show series and the next unread volume");
+        let p8 = Paragraph::new("t -> Show the trash to restore or purge deleted books");
+        let p9 = Paragraph::new("ctrl-z -> Undo the latest change");
+        let p10 = Paragraph::new("ctrl-r -> Redo the latest undone change");
+        let p11 = Paragraph::new("q -> Quit");
 
         frame.render_widget(p1, layout[0]);
         frame.render_widget(p2, layout[1]);
@@ -501,6 +544,165 @@ fn render_help_popup<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
         frame.render_widget(p5, layout[4]);
         frame.render_widget(p6, layout[5]);
         frame.render_widget(p7, layout[6]);
+        frame.render_widget(p8, layout[7]);
+        frame.render_widget(p9, layout[8]);
+        frame.render_widget(p10, layout[9]);
+        frame.render_widget(p11, layout[10]);
+    }
+}
+
+fn render_delete_popup(app: &mut App, frame: &mut Frame) {
+    if app.delete_popup {
+        if let Some(book) = app.selected_book() {
+            let block = Block::default().title("Delete").borders(Borders::ALL);
+            let area = centered_rect(40, 20, frame.area());
+            let text = vec![
+                Line::from(format!("Move \"{}\" to the trash?", book.title)),
+                Line::from(""),
+                Line::from("[y] yes   [n] no"),
+            ];
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(text)
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+        }
+    }
+}
+
+fn render_trash_popup(app: &mut App, frame: &mut Frame) {
+    if app.trash_popup {
+        let block = Block::default().title("Trash").borders(Borders::ALL);
+        let area = centered_rect(70, 60, frame.area());
+        let block_inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new("").block(block), area);
+        if let Some(state) = &mut app.trash_state {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+                .split(block_inner);
+
+            let rows: Vec<Row> = state
+                .books
+                .iter()
+                .map(|book| {
+                    Row::new(vec![
+                        book.id.to_string(),
+                        book.title.clone(),
+                        book.author.clone(),
+                        book.deleted_at.format("%Y-%m-%d %H:%M").to_string(),
+                    ])
+                })
+                .collect();
+            let books = Table::new(
+                rows,
+                [
+                    Constraint::Length(4),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(30),
+                    Constraint::Length(16),
+                ],
+            )
+            .header(
+                Row::new(vec!["Id", "Title", "Author", "Deleted"])
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .block(Block::default().borders(Borders::ALL))
+            .column_spacing(2)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">>");
+            frame.render_stateful_widget(books, layout[0], &mut state.table_state);
+
+            let info = match state.selected() {
+                Some(book) if state.confirm_purge => Paragraph::new(format!(
+                    "Purge \"{}\" for good? Only undo brings it back [y] yes [n] no",
+                    book.title
+                ))
+                .style(Style::default().fg(Color::Red)),
+                _ => Paragraph::new(
+                    "[j/k] to select a book, [r] to restore it, [p] to purge it, [esc] to close",
+                ),
+            };
+            frame.render_widget(info, layout[1]);
+        }
+    }
+}
+
+fn render_series_popup(app: &mut App, frame: &mut Frame) {
+    if app.series_popup {
+        let block = Block::default().title("Series").borders(Borders::ALL);
+        let area = centered_rect(70, 60, frame.area());
+        let block_inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new("").block(block), area);
+        if let Some(state) = &mut app.series_state {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+                .split(block_inner);
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+                .split(layout[0]);
+
+            let items: Vec<ListItem> = state
+                .series
+                .iter()
+                .map(|series| {
+                    ListItem::new(format!(
+                        "{} ({}/{})",
+                        series.name, series.read, series.volumes
+                    ))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .highlight_symbol(">>");
+            frame.render_stateful_widget(list, columns[0], &mut state.list_state);
+
+            let rows: Vec<Row> = state
+                .volumes
+                .iter()
+                .map(|book| {
+                    let read = if book.is_read() {
+                        "read"
+                    } else if state.next_unread == Some(book.id) {
+                        "next"
+                    } else {
+                        ""
+                    };
+                    let row = Row::new(vec![
+                        book.series_index.map(format_index).unwrap_or_default(),
+                        book.title.to_string(),
+                        read.to_string(),
+                    ]);
+                    match read {
+                        "read" => row.style(Style::default().fg(Color::DarkGray)),
+                        "next" => row.style(Style::default().add_modifier(Modifier::BOLD)),
+                        _ => row,
+                    }
+                })
+                .collect();
+            let volumes = Table::new(
+                rows,
+                [
+                    Constraint::Length(5),
+                    Constraint::Percentage(70),
+                    Constraint::Length(5),
+                ],
+            )
+            .header(Row::new(vec!["#", "Title", ""]).style(Style::default().fg(Color::Yellow)))
+            .block(Block::default().borders(Borders::ALL))
+            .column_spacing(2);
+            frame.render_widget(volumes, columns[1]);
+
+            let info = Paragraph::new("[j/k] to select a series, [esc] to close");
+            frame.render_widget(info, layout[1]);
+        }
     }
 }
 